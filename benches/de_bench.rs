@@ -0,0 +1,108 @@
+//! Measures the deserialization of a large generated inventory. Run with
+//! `cargo bench --bench de_bench`.
+
+use serde::Deserialize as _;
+use serde_derive::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Inventory {
+    name: String,
+    hosts: Vec<Host>,
+    groups: HashMap<String, Group>,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Host {
+    hostname: String,
+    ip: String,
+    ports: Vec<u16>,
+    enabled: bool,
+    weight: f64,
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Group {
+    description: String,
+    members: Vec<String>,
+    vars: HashMap<String, String>,
+}
+
+fn inventory(hosts: usize) -> String {
+    let mut doc = String::from("name: \"inventory\"\nhosts: [\n");
+    for i in 0..hosts {
+        let _ = write!(
+            doc,
+            "    hostname: \"host-{i}\"\n    ip: \"10.0.{}.{}\"\n    ports: [22, 80, 443]\n    enabled: true\n    weight: {}.5\n    tags: [\"web\", \"zone-{}\"]",
+            i / 256,
+            i % 256,
+            i,
+            i % 8,
+            i = i
+        );
+        doc.push_str(if i + 1 < hosts { ",\n" } else { "\n" });
+    }
+    doc.push_str("]\ngroups:\n");
+    for g in 0..hosts / 10 {
+        let _ = writeln!(
+            doc,
+            "    group_{g}:\n        description: \"Group {g}\"\n        members: [\"host-{g}\", \"host-{}\"]\n        vars:\n            env: \"prod\"\n            owner: \"team-{}\"",
+            g + 1,
+            g % 5,
+            g = g
+        );
+    }
+    doc
+}
+
+fn measure<F: FnMut()>(iterations: u32, mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    start.elapsed() / iterations
+}
+
+fn main() {
+    for &hosts in &[1_000, 10_000] {
+        let doc = inventory(hosts);
+        let parsed = gura::parse(&doc).unwrap();
+        let iterations = 5;
+
+        let parse = measure(iterations, || {
+            black_box(gura::parse(&doc).unwrap());
+        });
+        let clone = measure(iterations, || {
+            black_box(parsed.clone());
+        });
+        // Deserializes an already parsed document. The clone is measured apart
+        // and subtracted
+        let deserialize = measure(iterations, || {
+            let mut deserializer = serde_gura::Deserializer::from_gura_type(parsed.clone());
+            black_box(Inventory::deserialize(&mut deserializer).unwrap());
+        });
+        let from_str = measure(iterations, || {
+            black_box(serde_gura::from_str::<Inventory>(&doc).unwrap());
+        });
+        let from_str_direct = measure(iterations, || {
+            black_box(serde_gura::from_str_direct::<Inventory>(&doc).unwrap());
+        });
+
+        println!(
+            "{} hosts ({} KB): gura::parse {:?}, deserialize {:?}, from_str {:?}, from_str_direct {:?}",
+            hosts,
+            doc.len() / 1024,
+            parse,
+            deserialize.saturating_sub(clone),
+            from_str,
+            from_str_direct
+        );
+    }
+}
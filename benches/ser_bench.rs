@@ -0,0 +1,89 @@
+//! Compares `to_string` with `to_string_direct` on a large generated inventory.
+//! Run with `cargo bench --bench ser_bench`.
+
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+#[derive(Serialize)]
+struct Inventory {
+    name: String,
+    hosts: Vec<Host>,
+    groups: BTreeMap<String, Group>,
+}
+
+#[derive(Serialize)]
+struct Host {
+    hostname: String,
+    ip: String,
+    ports: Vec<u16>,
+    enabled: bool,
+    weight: f64,
+    tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Group {
+    description: String,
+    members: Vec<String>,
+    vars: BTreeMap<String, String>,
+}
+
+fn inventory(hosts: usize) -> Inventory {
+    Inventory {
+        name: "inventory".to_string(),
+        hosts: (0..hosts)
+            .map(|i| Host {
+                hostname: format!("host-{}", i),
+                ip: format!("10.0.{}.{}", i / 256, i % 256),
+                ports: vec![22, 80, 443],
+                enabled: true,
+                weight: i as f64 + 0.5,
+                tags: vec!["web".to_string(), format!("zone-{}", i % 8)],
+            })
+            .collect(),
+        groups: (0..hosts / 10)
+            .map(|g| {
+                let group = Group {
+                    description: format!("Group {}", g),
+                    members: vec![format!("host-{}", g), format!("host-{}", g + 1)],
+                    vars: vec![
+                        ("env".to_string(), "prod".to_string()),
+                        ("owner".to_string(), format!("team-{}", g % 5)),
+                    ]
+                    .into_iter()
+                    .collect(),
+                };
+                (format!("group_{}", g), group)
+            })
+            .collect(),
+    }
+}
+
+fn measure<F: FnMut()>(iterations: u32, mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    start.elapsed() / iterations
+}
+
+fn main() {
+    for &hosts in &[1_000, 10_000] {
+        let inventory = inventory(hosts);
+        let iterations = 5;
+
+        let tree = measure(iterations, || {
+            black_box(serde_gura::to_string(&inventory).unwrap());
+        });
+        let direct = measure(iterations, || {
+            black_box(serde_gura::to_string_direct(&inventory).unwrap());
+        });
+
+        println!(
+            "{} hosts: to_string {:?}, to_string_direct {:?}",
+            hosts, tree, direct
+        );
+    }
+}
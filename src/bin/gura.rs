@@ -0,0 +1,259 @@
+//! Command line tool to query, edit and convert Gura documents.
+//!
+//! Built with `cargo install serde_gura --features cli`.
+
+use gura::{parse, GuraType};
+use indexmap::IndexMap;
+use serde_gura::dump;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+const USAGE: &str = "Usage: gura <COMMAND> [ARGS] [FILE]
+
+Reads from FILE or from the standard input if FILE is missing or `-`.
+
+Commands:
+    get <PATH> [FILE]                 Prints the value at PATH (strings are printed unquoted)
+    set <PATH> <VALUE> [FILE] [-i]    Sets the Gura VALUE at PATH and prints the document
+    validate [FILE]                   Checks that the input is a valid Gura document
+    fmt [FILE] [-i]                   Prints the document in its canonical format
+    to-json [FILE]                    Converts a Gura document to JSON
+    from-json [FILE]                  Converts a JSON document to Gura
+
+Options:
+    -i, --in-place    Writes the result back to FILE instead of printing it
+
+PATH is a list of keys separated by dots, with array indexes between brackets
+(e.g. `services.web.ports[0]`).";
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(err) = run(args) {
+        eprintln!("gura: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> CliResult<()> {
+    let in_place = args.iter().any(|arg| arg == "-i" || arg == "--in-place");
+    let args: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| *arg != "-i" && *arg != "--in-place")
+        .collect();
+
+    let (command, rest) = match args.split_first() {
+        Some((command, rest)) => (*command, rest),
+        None => return Err(USAGE.into()),
+    };
+
+    match (command, rest) {
+        ("get", [path, file @ ..]) => {
+            let doc = read_gura(file)?;
+            let value = get(&doc, &parse_path(path)?)?;
+            match value {
+                GuraType::String(str) => println!("{}", str),
+                _ => println!("{}", dump(value)),
+            }
+            Ok(())
+        }
+        ("set", [path, value, file @ ..]) => {
+            let mut doc = read_gura(file)?;
+            let value = parse_value(value)?;
+            set(&mut doc, &parse_path(path)?, value)?;
+            write_output(file, in_place, &document(&doc))
+        }
+        ("validate", file) => {
+            read_gura(file)?;
+            Ok(())
+        }
+        ("fmt", file) => {
+            let doc = read_gura(file)?;
+            write_output(file, in_place, &document(&doc))
+        }
+        ("to-json", file) => {
+            println!("{}", serde_gura::json::from_gura(&read_input(file)?)?);
+            Ok(())
+        }
+        ("from-json", file) => {
+            print_lines(&serde_gura::json::to_gura(&read_input(file)?)?);
+            Ok(())
+        }
+        ("help", _) | ("-h", _) | ("--help", _) => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn file_arg<'a>(file: &[&'a str]) -> CliResult<Option<&'a str>> {
+    match file {
+        [] | ["-"] => Ok(None),
+        [path] => Ok(Some(path)),
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn read_input(file: &[&str]) -> CliResult<String> {
+    match file_arg(file)? {
+        Some(path) => Ok(fs::read_to_string(path)?),
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            Ok(text)
+        }
+    }
+}
+
+fn read_gura(file: &[&str]) -> CliResult<GuraType> {
+    Ok(parse(&read_input(file)?).map_err(|e| e.to_string())?)
+}
+
+/// Writes a document, which is empty if it has no keys as `empty` is not a
+/// valid document
+fn document(doc: &GuraType) -> String {
+    match doc {
+        GuraType::Object(obj) if obj.is_empty() => String::new(),
+        _ => dump(doc),
+    }
+}
+
+/// Text ended by a new line, unless it is empty
+fn lines(text: &str) -> String {
+    if text.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", text)
+    }
+}
+
+fn print_lines(text: &str) {
+    print!("{}", lines(text));
+}
+
+fn write_output(file: &[&str], in_place: bool, text: &str) -> CliResult<()> {
+    match file_arg(file)? {
+        Some(path) if in_place => Ok(fs::write(path, lines(text))?),
+        None if in_place => Err("--in-place requires a FILE".into()),
+        _ => {
+            print_lines(text);
+            Ok(())
+        }
+    }
+}
+
+/// Parses a single Gura value, as it would appear at the right of a key.
+fn parse_value(value: &str) -> CliResult<GuraType> {
+    let parsed = parse(&format!("value: {}", value)).map_err(|e| e.to_string())?;
+    match parsed {
+        GuraType::Object(mut obj) => obj
+            .swap_remove("value")
+            .ok_or_else(|| format!("Invalid value \"{}\"", value).into()),
+        _ => Err(format!("Invalid value \"{}\"", value).into()),
+    }
+}
+
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Parses a path like `services.web.ports[0]`. Every part between dots must
+/// start with a key, so paths like `a..b`, `a.` or `.a` are rejected.
+fn parse_path(path: &str) -> CliResult<Vec<Segment>> {
+    let invalid = || format!("Invalid path \"{}\"", path);
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let (key, mut indexes) = match part.find('[') {
+            Some(pos) => part.split_at(pos),
+            None => (part, ""),
+        };
+
+        let is_key_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        if key.is_empty() || !key.chars().all(is_key_char) {
+            return Err(invalid().into());
+        }
+        segments.push(Segment::Key(key.to_string()));
+
+        while !indexes.is_empty() {
+            if !indexes.starts_with('[') {
+                return Err(invalid().into());
+            }
+            let end = indexes.find(']').ok_or_else(invalid)?;
+            let index = indexes[1..end]
+                .parse()
+                .map_err(|_| format!("Invalid array index in path \"{}\"", path))?;
+            segments.push(Segment::Index(index));
+            indexes = &indexes[end + 1..];
+        }
+    }
+    Ok(segments)
+}
+
+fn get<'a>(doc: &'a GuraType, path: &[Segment]) -> CliResult<&'a GuraType> {
+    let mut current = doc;
+    for segment in path {
+        current = match (segment, current) {
+            (Segment::Key(key), GuraType::Object(obj)) => obj
+                .get(key)
+                .ok_or_else(|| format!("Key \"{}\" not found", key))?,
+            (Segment::Index(idx), GuraType::Array(array)) => array
+                .get(*idx)
+                .ok_or_else(|| format!("Index {} out of bounds", idx))?,
+            (Segment::Key(key), _) => {
+                return Err(format!("Can not get key \"{}\" of a non object value", key).into())
+            }
+            (Segment::Index(idx), _) => {
+                return Err(format!("Can not get index {} of a non array value", idx).into())
+            }
+        };
+    }
+    Ok(current)
+}
+
+/// Sets a value at the given path, creating the missing intermediate objects.
+fn set(doc: &mut GuraType, path: &[Segment], value: GuraType) -> CliResult<()> {
+    let (last, parents) = path.split_last().ok_or("Empty path")?;
+
+    let mut current = doc;
+    for segment in parents {
+        current = match (segment, current) {
+            (Segment::Key(key), GuraType::Object(obj)) => obj
+                .entry(key.clone())
+                .or_insert_with(|| GuraType::Object(IndexMap::new())),
+            (Segment::Index(idx), GuraType::Array(array)) => array
+                .get_mut(*idx)
+                .ok_or_else(|| format!("Index {} out of bounds", idx))?,
+            (Segment::Key(key), _) => {
+                return Err(format!("Can not set key \"{}\" of a non object value", key).into())
+            }
+            (Segment::Index(idx), _) => {
+                return Err(format!("Can not set index {} of a non array value", idx).into())
+            }
+        };
+    }
+
+    match (last, current) {
+        (Segment::Key(key), GuraType::Object(obj)) => {
+            obj.insert(key.clone(), value);
+        }
+        (Segment::Index(idx), GuraType::Array(array)) => {
+            let elem = array
+                .get_mut(*idx)
+                .ok_or_else(|| format!("Index {} out of bounds", idx))?;
+            *elem = value;
+        }
+        (Segment::Key(key), _) => {
+            return Err(format!("Can not set key \"{}\" of a non object value", key).into())
+        }
+        (Segment::Index(idx), _) => {
+            return Err(format!("Can not set index {} of a non array value", idx).into())
+        }
+    }
+    Ok(())
+}
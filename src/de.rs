@@ -1,19 +1,276 @@
-use crate::error::{Error, Result};
-use gura::{parse, GuraType};
+use crate::enum_repr::{EnumRepr, VariantNames};
+use crate::error::{Error, ErrorKind, Result};
+use crate::parser::Parser;
+use crate::stream::DuplicateKeys;
+use gura::GuraType;
+use indexmap::IndexMap;
 use serde::de::{
     self, Deserialize, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess,
     SeqAccess, VariantAccess, Visitor,
 };
-use std::collections::VecDeque;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+/// Options shared by a `Deserializer` and the deserializers created for the
+/// nested values of the document.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Options {
+    pub(crate) int_to_float: bool,
+    pub(crate) float_to_int: bool,
+    pub(crate) lenient_strings: bool,
+    pub(crate) empty_as_unit: bool,
+    pub(crate) enum_repr: EnumRepr,
+    pub(crate) variant_names: VariantNames,
+    pub(crate) lenient_variants: bool,
+    pub(crate) variant_indices: bool,
+    pub(crate) duplicate_keys: DuplicateKeys,
+}
+
+/// Parses a Gura string, with the line and column of the error if it fails
+pub(crate) fn parse(s: &str) -> Result<GuraType> {
+    gura::parse(s).map_err(|err| Error::from_gura(err, s))
+}
+
+/// Limits for untrusted input. The text is checked before it is parsed and
+/// the document before any value is deserialized. No limit is set by default.
+///
+/// # Example
+///
+/// ```
+/// use serde_gura::{ErrorKind, Limits};
+///
+/// let limits = Limits::new().max_depth(2).max_input_size(64 * 1024);
+/// let err = serde_gura::from_str_with_limits::<serde_json::Value>("a: [[1]]", &limits)
+///     .unwrap_err();
+/// assert_eq!(err.kind(), &ErrorKind::DepthLimit(2));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) max_nodes: Option<usize>,
+    pub(crate) max_string_length: Option<usize>,
+    pub(crate) max_input_size: Option<usize>,
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Limits::default()
+    }
+
+    /// Fails with `ErrorKind::DepthLimit` if arrays and objects are nested more
+    /// than `depth` levels. The document itself is the first level.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Fails with `ErrorKind::NodeLimit` if the document has more than `nodes`
+    /// values, counting the document itself, arrays and objects.
+    pub fn max_nodes(mut self, nodes: usize) -> Self {
+        self.max_nodes = Some(nodes);
+        self
+    }
+
+    /// Fails with `ErrorKind::StringLengthLimit` if a string or a key is longer
+    /// than `len` bytes.
+    pub fn max_string_length(mut self, len: usize) -> Self {
+        self.max_string_length = Some(len);
+        self
+    }
+
+    /// Fails with `ErrorKind::InputSizeLimit` if the text is longer than `size`
+    /// bytes. Checked before the text is parsed.
+    pub fn max_input_size(mut self, size: usize) -> Self {
+        self.max_input_size = Some(size);
+        self
+    }
+
+    pub(crate) fn check_input(&self, s: &str) -> Result<()> {
+        match self.max_input_size {
+            Some(max) if s.len() > max => Err(ErrorKind::InputSizeLimit(max).into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_nodes(&self, nodes: usize) -> Result<()> {
+        match self.max_nodes {
+            Some(max) if nodes > max => Err(ErrorKind::NodeLimit(max).into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks a whole document
+    fn check(&self, obj: &GuraType) -> Result<()> {
+        let mut nodes = 0;
+        self.check_value(obj, 0, &mut nodes)
+    }
+
+    /// Checks a value found at `depth` and the values nested in it, counting
+    /// them in `nodes`
+    fn check_value(&self, value: &GuraType, depth: usize, nodes: &mut usize) -> Result<()> {
+        *nodes += 1;
+        self.check_nodes(*nodes)?;
+
+        match value {
+            GuraType::String(str) => self.check_string(str),
+            GuraType::Array(elements) => {
+                self.check_depth(depth + 1)?;
+                for elem in elements {
+                    self.check_value(elem, depth + 1, nodes)?;
+                }
+                Ok(())
+            }
+            GuraType::Object(obj) => {
+                self.check_depth(depth + 1)?;
+                for (key, value) in obj {
+                    self.check_string(key)?;
+                    self.check_value(value, depth + 1, nodes)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_depth(&self, depth: usize) -> Result<()> {
+        match self.max_depth {
+            Some(max) if depth > max => Err(ErrorKind::DepthLimit(max).into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_string(&self, str: &str) -> Result<()> {
+        match self.max_string_length {
+            Some(max) if str.len() > max => Err(ErrorKind::StringLengthLimit(max).into()),
+            _ => Ok(()),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Deserializer {
     obj: GuraType,
+    options: Options,
 }
 
 impl Deserializer {
     pub fn from_gura_type(obj: GuraType) -> Self {
-        Deserializer { obj }
+        Deserializer::with_options(obj, Options::default())
+    }
+
+    /// Same as `from_gura_type`, failing if the document exceeds `limits`
+    pub fn from_gura_type_with_limits(obj: GuraType, limits: &Limits) -> Result<Self> {
+        limits.check(&obj)?;
+        Ok(Deserializer::from_gura_type(obj))
+    }
+
+    /// Parses a Gura string. Options can be set on the returned deserializer
+    /// before passing it to `T::deserialize`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    ///
+    /// let mut deserializer = serde_gura::Deserializer::from_str("timeout: 30")
+    ///     .unwrap()
+    ///     .int_to_float(true);
+    /// let value = std::collections::HashMap::<String, f64>::deserialize(&mut deserializer).unwrap();
+    /// assert_eq!(value["timeout"], 30.0);
+    /// ```
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self> {
+        let parsed = parse(s)?;
+        Ok(Deserializer::from_gura_type(parsed))
+    }
+
+    /// Same as `from_str`, failing if the text or the document exceed
+    /// `limits`. The size of the text is checked before it is parsed, and the
+    /// depth and the number of values while the text is read once before
+    /// parsing it, as the `gura` parser would overflow the stack on deeply
+    /// nested input. The syntax errors found while doing so are returned as
+    /// `TextDeserializer` reports them.
+    pub fn from_str_with_limits(s: &str, limits: &Limits) -> Result<Self> {
+        limits.check_input(s)?;
+        if limits.max_depth.is_some() || limits.max_nodes.is_some() {
+            Parser::check_limits(s, *limits)?;
+        }
+        Deserializer::from_gura_type_with_limits(parse(s)?, limits)
+    }
+
+    pub(crate) fn with_options(obj: GuraType, options: Options) -> Self {
+        Deserializer { obj, options }
+    }
+
+    /// Moves the value out of the deserializer. Called once the value is known
+    /// to be of the expected type, so nested values are moved instead of cloned
+    fn take(&mut self) -> GuraType {
+        std::mem::replace(&mut self.obj, GuraType::Null)
+    }
+
+    /// Allows Gura integers to be deserialized into floats as long as no
+    /// precision is lost (e.g. `timeout: 30` into an `f64`). Disabled by
+    /// default, as the Gura spec does not convert between integers and floats.
+    pub fn int_to_float(mut self, enabled: bool) -> Self {
+        self.options.int_to_float = enabled;
+        self
+    }
+
+    /// Allows Gura floats without fractional part to be deserialized into
+    /// integers (e.g. `retries: 3.0` into a `u32`). Floats out of the range of
+    /// the integer type fail with `ErrorKind::ExpectedInteger`. Disabled by
+    /// default.
+    pub fn float_to_int(mut self, enabled: bool) -> Self {
+        self.options.float_to_int = enabled;
+        self
+    }
+
+    /// Allows strings to be deserialized into integers, floats and bools when
+    /// their content parses as the requested type (e.g. `port: "8080"` into a
+    /// `u16` or `enabled: "true"` into a `bool`). Disabled by default.
+    pub fn lenient_strings(mut self, enabled: bool) -> Self {
+        self.options.lenient_strings = enabled;
+        self
+    }
+
+    /// Allows the `empty` keyword to be deserialized into unit and unit
+    /// structs, besides `null`. Disabled by default.
+    pub fn empty_as_unit(mut self, enabled: bool) -> Self {
+        self.options.empty_as_unit = enabled;
+        self
+    }
+
+    /// Sets how enum variants are expected to be written. It must match the
+    /// representation used to serialize them. Defaults to `EnumRepr::External`.
+    pub fn enum_repr(mut self, repr: EnumRepr) -> Self {
+        self.options.enum_repr = repr;
+        self
+    }
+
+    /// Sets how variant names are expected to be written, both for enum values
+    /// and for map keys. Defaults to `VariantNames::AsIs`.
+    pub fn variant_names(mut self, names: VariantNames) -> Self {
+        self.options.variant_names = names;
+        self
+    }
+
+    /// Matches enum variants ignoring case, underscores and dashes when no
+    /// variant is written exactly as expected, so `"info"` or `"INFO"` are read
+    /// as `Info` and `"max-value"` as `MaxValue`. Names matching more than one
+    /// variant fail with `ErrorKind::AmbiguousVariant`. Disabled by default.
+    pub fn lenient_variants(mut self, enabled: bool) -> Self {
+        self.options.lenient_variants = enabled;
+        self
+    }
+
+    /// Allows enum variants to be identified by their index, so `mode: 2` is
+    /// read as the third variant. Indices are accepted where a variant name is
+    /// expected as a value (unit variants and `EnumRepr::Adjacent` tags), not as
+    /// keys. Disabled by default.
+    pub fn variant_indices(mut self, enabled: bool) -> Self {
+        self.options.variant_indices = enabled;
+        self
     }
 }
 
@@ -22,58 +279,136 @@ pub fn from_str<'a, T>(s: &'a str) -> Result<T>
 where
     T: Deserialize<'a>,
 {
-    let parsed = parse(s).map_err(|e| Error::Syntax(e.to_string()))?;
-    let mut deserializer = Deserializer::from_gura_type(parsed);
+    let mut deserializer = Deserializer::from_str(s)?;
     let result = T::deserialize(&mut deserializer)?;
     Ok(result)
 }
 
+/// Same as `from_str`, failing if the text or the document exceed `limits`
+pub fn from_str_with_limits<'a, T>(s: &'a str, limits: &Limits) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_str_with_limits(s, limits)?;
+    T::deserialize(&mut deserializer)
+}
+
+fn int_to_float(int_value: i128) -> Result<f64> {
+    let float_value = int_value as f64;
+    if float_value as i128 == int_value {
+        Ok(float_value)
+    } else {
+        Err(ErrorKind::InexactFloat(int_value.to_string()).into())
+    }
+}
+
+/// Returns the float if it has no fractional part and it is between `min` and `max`
+fn integral_float(value: f64, min: f64, max: f64) -> Option<f64> {
+    if value.fract() == 0.0 && value >= min && value <= max {
+        Some(value)
+    } else {
+        None
+    }
+}
+
 // Serde is not a parsing library. That's why Gura Rust parser is used in this crate
 impl Deserializer {
-    fn parse_bool(&mut self) -> Result<bool> {
-        if let GuraType::Bool(boolean) = self.obj {
-            Ok(boolean)
-        } else {
-            Err(Error::ExpectedBoolean)
-        }
+    /// Name of the variant identified by a string or, if `variant_indices` is
+    /// enabled, by its index
+    fn variant<'a>(&self, id: &'a GuraType, variants: &'static [&'static str]) -> Result<&'a str> {
+        let index = match id {
+            GuraType::String(str) => {
+                return self.options.variant_names.resolve(
+                    str,
+                    variants,
+                    self.options.lenient_variants,
+                )
+            }
+            GuraType::Integer(index) if self.options.variant_indices => *index as i128,
+            GuraType::BigInteger(index) if self.options.variant_indices => *index,
+            _ => return Err(ErrorKind::ExpectedEnum.into()),
+        };
+
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| variants.get(index))
+            .copied()
+            .ok_or_else(|| {
+                ErrorKind::Message(format!(
+                    "invalid value: integer `{}`, expected variant index 0 <= i < {}",
+                    index,
+                    variants.len()
+                ))
+                .into()
+            })
     }
 
-    fn parse_unsigned(&mut self) -> Result<usize> {
+    fn parse_bool(&mut self) -> Result<bool> {
         match &self.obj {
-            GuraType::Integer(int_value) => Ok(*int_value as usize),
-            GuraType::BigInteger(big_int_value) => Ok(*big_int_value as usize),
-            GuraType::Pair(key, ..) => {
-                if let Ok(key_unsigned) = key.parse::<usize>() {
-                    Ok(key_unsigned)
-                } else {
-                    Err(Error::ExpectedInteger)
-                }
+            GuraType::Bool(boolean) => Ok(*boolean),
+            GuraType::String(str) if self.options.lenient_strings => {
+                str.parse().map_err(|_| ErrorKind::ExpectedBoolean.into())
             }
-            _ => Err(Error::ExpectedInteger),
+            _ => Err(ErrorKind::ExpectedBoolean.into()),
         }
     }
 
-    fn parse_signed(&mut self) -> Result<isize> {
-        match &self.obj {
-            GuraType::Integer(int_value) => Ok(*int_value),
-            GuraType::Pair(key, ..) => {
-                if let Ok(key_signed) = key.parse::<isize>() {
-                    Ok(key_signed)
-                } else {
-                    Err(Error::ExpectedInteger)
+    /// Reads an integer of type `T`. Values out of the range of `T` fail with
+    /// `ExpectedInteger` instead of being truncated
+    fn parse_integer<T>(&mut self) -> Result<T>
+    where
+        T: TryFrom<i128> + FromStr,
+    {
+        let int_value = match &self.obj {
+            GuraType::Integer(int_value) => *int_value as i128,
+            GuraType::BigInteger(big_int_value) => *big_int_value,
+            GuraType::Float(float_value) if self.options.float_to_int => {
+                match integral_float(*float_value, i128::MIN as f64, i128::MAX as f64) {
+                    Some(int_value) => int_value as i128,
+                    None => return Err(ErrorKind::ExpectedInteger.into()),
                 }
             }
-            _ => {
-                Err(Error::ExpectedInteger)
+            GuraType::String(str) if self.options.lenient_strings => {
+                return str.parse().map_err(|_| ErrorKind::ExpectedInteger.into())
             }
-        }
+            _ => return Err(ErrorKind::ExpectedInteger.into()),
+        };
+
+        T::try_from(int_value).map_err(|_| ErrorKind::ExpectedInteger.into())
     }
 
     fn parse_float(&mut self) -> Result<f64> {
         match &self.obj {
             GuraType::Float(float_value) => Ok(*float_value),
-            _ => Err(Error::ExpectedFloat),
+            GuraType::Integer(int_value) if self.options.int_to_float => {
+                int_to_float(*int_value as i128)
+            }
+            GuraType::BigInteger(big_int_value) if self.options.int_to_float => {
+                int_to_float(*big_int_value)
+            }
+            GuraType::String(str) if self.options.lenient_strings => {
+                str.parse().map_err(|_| ErrorKind::ExpectedFloat.into())
+            }
+            _ => Err(ErrorKind::ExpectedFloat.into()),
+        }
+    }
+
+    fn parse_f32(&mut self) -> Result<f32> {
+        if let GuraType::String(str) = &self.obj {
+            if self.options.lenient_strings {
+                return str.parse().map_err(|_| ErrorKind::ExpectedFloat.into());
+            }
         }
+
+        let float_value = self.parse_float()?;
+        let single = float_value as f32;
+
+        // Coerced integers must be exact in single precision too
+        let is_int = matches!(self.obj, GuraType::Integer(_) | GuraType::BigInteger(_));
+        if is_int && single as f64 != float_value {
+            return Err(ErrorKind::InexactFloat(float_value.to_string()).into());
+        }
+        Ok(single)
     }
 
     fn parse_char(&mut self) -> Result<char> {
@@ -81,18 +416,20 @@ impl Deserializer {
             if str.len() == 1 {
                 Ok(str.chars().next().unwrap())
             } else {
-                Err(Error::ExpectedChar)
+                Err(ErrorKind::ExpectedChar.into())
             }
         } else {
-            Err(Error::ExpectedChar)
+            Err(ErrorKind::ExpectedChar.into())
         }
     }
 
     fn parse_string(&mut self) -> Result<String> {
-        match &self.obj {
-            GuraType::Pair(key, _, _) => Ok(key.clone()),
-            GuraType::String(str_value) => Ok(str_value.clone()),
-            _ => Err(Error::ExpectedString),
+        match self.take() {
+            GuraType::String(str_value) => Ok(str_value),
+            other => {
+                self.obj = other;
+                Err(ErrorKind::ExpectedString.into())
+            }
         }
     }
 }
@@ -111,11 +448,10 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer {
             GuraType::Bool(_) => self.deserialize_bool(visitor),
             GuraType::Float(_) => self.deserialize_f64(visitor),
             GuraType::Integer(_) => self.deserialize_i64(visitor),
-            GuraType::Null => self.deserialize_unit(visitor),
+            GuraType::Null => visitor.visit_unit(),
             GuraType::Object(_) => self.deserialize_map(visitor),
-            GuraType::Pair(..) => self.deserialize_identifier(visitor),
             GuraType::String(_) => self.deserialize_string(visitor),
-            _ => Err(Error::InvalidType),
+            _ => Err(ErrorKind::InvalidType.into()),
         }
     }
 
@@ -130,63 +466,77 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i8(self.parse_signed()? as i8)
+        visitor.visit_i8(self.parse_integer()?)
     }
 
     fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i16(self.parse_signed()? as i16)
+        visitor.visit_i16(self.parse_integer()?)
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i32(self.parse_signed()? as i32)
+        visitor.visit_i32(self.parse_integer()?)
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i64(self.parse_signed()? as i64)
+        visitor.visit_i64(self.parse_integer()?)
+    }
+
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i128(self.parse_integer()?)
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u8(self.parse_unsigned()? as u8)
+        visitor.visit_u8(self.parse_integer()?)
     }
 
     fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u16(self.parse_unsigned()? as u16)
+        visitor.visit_u16(self.parse_integer()?)
     }
 
     fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u32(self.parse_unsigned()? as u32)
+        visitor.visit_u32(self.parse_integer()?)
     }
 
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u64(self.parse_unsigned()? as u64)
+        visitor.visit_u64(self.parse_integer()?)
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u128(self.parse_integer()?)
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_f32(self.parse_float()? as f32)
+        visitor.visit_f32(self.parse_f32()?)
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
@@ -242,11 +592,19 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer {
         }
     }
 
-    fn deserialize_unit<V>(self, _visitor: V) -> Result<V::Value>
+    // Unit is serialized as `null`. `empty` is accepted too if `empty_as_unit`
+    // is enabled
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        Err(Error::UnitNotSupported)
+        match &self.obj {
+            GuraType::Null => visitor.visit_unit(),
+            GuraType::Object(obj) if obj.is_empty() && self.options.empty_as_unit => {
+                visitor.visit_unit()
+            }
+            _ => Err(ErrorKind::ExpectedNull.into()),
+        }
     }
 
     fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
@@ -267,14 +625,16 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer {
     where
         V: Visitor<'de>,
     {
-        match self.obj {
-            GuraType::Array(_) => {
-                let obj = self.obj.clone();
-                let value = visitor.visit_seq(CommaSeparated::new(obj))?;
-                Ok(value)
+        let elements = match self.take() {
+            GuraType::Array(elements) => elements,
+            // `empty` is accepted as an empty collection too
+            GuraType::Object(obj) if obj.is_empty() => Vec::new(),
+            other => {
+                self.obj = other;
+                return Err(ErrorKind::ExpectedArray.into());
             }
-            _ => Err(Error::ExpectedArray),
-        }
+        };
+        visitor.visit_seq(CommaSeparated::new(elements, self.options.clone()))
     }
 
     // Tuples look just like sequences in Gura (arrays)
@@ -305,12 +665,12 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer {
     where
         V: Visitor<'de>,
     {
-        if let GuraType::Object(_) = &self.obj {
-            let obj_aux = self.obj.clone();
-            let value = visitor.visit_map(CommaSeparated::new(obj_aux))?;
-            Ok(value)
-        } else {
-            Err(Error::ExpectedMap)
+        match self.take() {
+            GuraType::Object(obj) => visitor.visit_map(KeyValues::new(obj, self.options.clone())),
+            other => {
+                self.obj = other;
+                Err(ErrorKind::ExpectedMap.into())
+            }
         }
     }
 
@@ -333,17 +693,51 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer {
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
-        _variants: &'static [&'static str],
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        match &self.obj {
-            GuraType::String(str) => visitor.visit_enum((str.clone()).into_deserializer()),
-            GuraType::Object(_) => visitor.visit_enum(Enum::new(self.obj.clone())),
-            _ => Err(Error::ExpectedEnum),
+        let variant = match (self.options.enum_repr, &self.obj) {
+            // The variant is the only key of the object
+            (EnumRepr::External, GuraType::Object(obj)) if !obj.is_empty() => {
+                if obj.len() != 1 {
+                    return Err(ErrorKind::ExpectedEnum.into());
+                }
+                let key = obj.keys().next().unwrap();
+                self.options
+                    .variant_names
+                    .resolve(key, variants, self.options.lenient_variants)?
+            }
+            (EnumRepr::External, id) => self.variant(id, variants)?,
+            (EnumRepr::Adjacent { tag, content }, GuraType::Object(obj)) => {
+                let expected_len = 1 + obj.contains_key(content) as usize;
+                if obj.len() != expected_len {
+                    return Err(ErrorKind::ExpectedEnum.into());
+                }
+                self.variant(obj.get(tag).ok_or(ErrorKind::ExpectedEnum)?, variants)?
+            }
+            _ => return Err(ErrorKind::ExpectedEnum.into()),
         }
+        .to_string();
+
+        // Moves the variant value out of the object
+        let value = match (self.options.enum_repr, self.take()) {
+            (EnumRepr::External, GuraType::Object(obj)) => {
+                obj.into_iter().next().map(|(_, value)| value)
+            }
+            (EnumRepr::Adjacent { content, .. }, GuraType::Object(mut obj)) => {
+                obj.swap_remove(content)
+            }
+            _ => None,
+        };
+
+        visitor.visit_enum(Enum {
+            variant,
+            value,
+            options: self.options.clone(),
+        })
     }
 
     // An identifier in Serde is the type that identifies a field of a struct or
@@ -354,9 +748,8 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer {
         V: Visitor<'de>,
     {
         match &self.obj {
-            GuraType::Pair(key, _, _) => visitor.visit_string(key.clone()),
-            GuraType::String(str) => visitor.visit_string(str.to_string()),
-            _ => Err(Error::ExpectedIdentifier),
+            GuraType::String(str) => visitor.visit_str(str),
+            _ => Err(ErrorKind::ExpectedIdentifier.into()),
         }
     }
 
@@ -368,44 +761,18 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer {
     }
 }
 
-// Struct helper to parse Gura objects and arrays
+// Struct helper to parse Gura arrays. Elements are moved out of the array as
+// they are deserialized
 struct CommaSeparated {
-    is_empty: bool,
-    vec: VecDeque<(String, GuraType)>,
+    elements: std::vec::IntoIter<GuraType>,
+    options: Options,
 }
 
 impl CommaSeparated {
-    fn new(obj: GuraType) -> Self {
-        let it: VecDeque<(String, GuraType)> = match obj {
-            GuraType::Object(_) => obj
-                .iter()
-                .unwrap()
-                .map(|(key, elem)| (key.clone(), elem.clone()))
-                .collect(),
-            GuraType::Array(ar) => ar
-                .iter()
-                .map(|elem| (String::new(), elem.clone()))
-                .collect(),
-            _ => VecDeque::new(),
-        };
-
-        let is_empty = it.is_empty();
-        CommaSeparated { vec: it, is_empty }
-    }
-
-    fn peek_next_elem(&mut self) -> Result<(String, GuraType)> {
-        if let Some(elem) = self.vec.front() {
-            Ok(elem.clone())
-        } else {
-            Err(Error::ExpectedMap)
-        }
-    }
-
-    fn get_next_elem(&mut self) -> Result<(String, GuraType)> {
-        if let Some(elem) = self.vec.pop_front() {
-            Ok(elem)
-        } else {
-            Err(Error::ExpectedMap)
+    fn new(elements: Vec<GuraType>, options: Options) -> Self {
+        CommaSeparated {
+            elements: elements.into_iter(),
+            options,
         }
     }
 }
@@ -419,34 +786,58 @@ impl<'de> SeqAccess<'de> for CommaSeparated {
     where
         T: DeserializeSeed<'de>,
     {
-        if let Ok((_, elem)) = self.get_next_elem() {
-            let mut de = Deserializer::from_gura_type(elem);
-            seed.deserialize(&mut de).map(Some)
-        } else {
-            Ok(None)
+        match self.elements.next() {
+            Some(elem) => {
+                let mut de = Deserializer::with_options(elem, self.options.clone());
+                seed.deserialize(&mut de).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+// Struct helper to parse Gura objects. Entries are moved out of the object as
+// they are deserialized, the value is kept until `next_value_seed` is called
+struct KeyValues {
+    entries: indexmap::map::IntoIter<String, GuraType>,
+    value: Option<GuraType>,
+    options: Options,
+}
+
+impl KeyValues {
+    fn new(obj: IndexMap<String, GuraType>, options: Options) -> Self {
+        KeyValues {
+            entries: obj.into_iter(),
+            value: None,
+            options,
         }
     }
 }
 
 // `MapAccess` is provided to the `Visitor` to give it the ability to iterate
 // through entries of the map.
-impl<'de> MapAccess<'de> for CommaSeparated {
+impl<'de> MapAccess<'de> for KeyValues {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
     where
         K: DeserializeSeed<'de>,
     {
-        // If it's an empty objects thats represented by 'empty' keyword
-        if self.is_empty {
-            return Ok(None);
-        }
-
-        if let Ok((key, elem)) = self.peek_next_elem() {
-            let mut de = Deserializer::from_gura_type(GuraType::Pair(key, Box::new(elem), 0));
-            seed.deserialize(&mut de).map(Some)
-        } else {
-            Ok(None)
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(MapKeyDeserializer {
+                    key: Cow::Owned(key),
+                    variant_names: self.options.variant_names,
+                    lenient_variants: self.options.lenient_variants,
+                })
+                .map(Some)
+            }
+            None => Ok(None),
         }
     }
 
@@ -454,39 +845,176 @@ impl<'de> MapAccess<'de> for CommaSeparated {
     where
         V: DeserializeSeed<'de>,
     {
-        let (_, elem) = self.get_next_elem()?;
-        let mut de = Deserializer::from_gura_type(elem);
+        let elem = self.value.take().ok_or(ErrorKind::ExpectedMap)?;
+        let mut de = Deserializer::with_options(elem, self.options.clone());
         seed.deserialize(&mut de)
     }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
 }
 
+// Deserializes map keys and enum variant names. Gura keys are always strings,
+// so they are parsed into the type the `Visitor` expects.
+pub(crate) struct MapKeyDeserializer<'a> {
+    pub(crate) key: Cow<'a, str>,
+    pub(crate) variant_names: VariantNames,
+    pub(crate) lenient_variants: bool,
+}
+
+impl MapKeyDeserializer<'_> {
+    fn parse<T: std::str::FromStr>(&self, error: Error) -> Result<T> {
+        self.key.parse().map_err(|_| error)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for MapKeyDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.key {
+            Cow::Borrowed(key) => visitor.visit_borrowed_str(key),
+            Cow::Owned(key) => visitor.visit_string(key),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_bool(self.parse(ErrorKind::ExpectedBoolean.into())?)
+    }
+
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i8(self.parse(ErrorKind::ExpectedInteger.into())?)
+    }
+
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i16(self.parse(ErrorKind::ExpectedInteger.into())?)
+    }
+
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i32(self.parse(ErrorKind::ExpectedInteger.into())?)
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i64(self.parse(ErrorKind::ExpectedInteger.into())?)
+    }
+
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i128(self.parse(ErrorKind::ExpectedInteger.into())?)
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u8(self.parse(ErrorKind::ExpectedInteger.into())?)
+    }
+
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u16(self.parse(ErrorKind::ExpectedInteger.into())?)
+    }
+
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u32(self.parse(ErrorKind::ExpectedInteger.into())?)
+    }
+
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u64(self.parse(ErrorKind::ExpectedInteger.into())?)
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u128(self.parse(ErrorKind::ExpectedInteger.into())?)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_char(self.parse(ErrorKind::ExpectedChar.into())?)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let variant = self
+            .variant_names
+            .resolve(&self.key, variants, self.lenient_variants)?;
+        visitor.visit_enum(variant.to_string().into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        f32 f64 str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Variant of an enum. `value` is `None` for unit variants.
 #[derive(Debug)]
 struct Enum {
-    vec: VecDeque<(String, GuraType)>,
+    variant: String,
+    value: Option<GuraType>,
+    options: Options,
 }
 
 impl Enum {
-    fn new(obj: GuraType) -> Self {
-        let it: VecDeque<(String, GuraType)> = match obj {
-            GuraType::Object(_) => obj
-                .iter()
-                .unwrap()
-                .map(|(key, elem)| (key.clone(), elem.clone()))
-                .collect(),
-            GuraType::Array(ar) => ar
-                .iter()
-                .map(|elem| (String::new(), elem.clone()))
-                .collect(),
-            _ => VecDeque::new(),
-        };
-        Enum { vec: it }
-    }
-
-    fn peek_next_elem(&self) -> Result<(String, GuraType)> {
-        if let Some(elem) = self.vec.front() {
-            Ok(elem.clone())
-        } else {
-            Err(Error::ExpectedMap)
+    // Deserializer of the value of a newtype, tuple or struct variant
+    fn value_deserializer(self) -> Result<Deserializer> {
+        match self.value {
+            Some(value) => Ok(Deserializer::with_options(value, self.options)),
+            None => Err(ErrorKind::ExpectedVariantValue.into()),
         }
     }
 }
@@ -497,14 +1025,15 @@ impl<'de> EnumAccess<'de> for Enum {
     type Error = Error;
     type Variant = Self;
 
-    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    fn variant_seed<V>(mut self, seed: V) -> Result<(V::Value, Self::Variant)>
     where
         V: DeserializeSeed<'de>,
     {
-        // Generates a Gura pair to check the Serde variant
-        let (key, elem) = self.peek_next_elem()?;
-        let mut de = Deserializer::from_gura_type(GuraType::Pair(key, Box::new(elem), 0));
-        let val = seed.deserialize(&mut de)?;
+        let val = seed.deserialize(MapKeyDeserializer {
+            key: Cow::Owned(std::mem::take(&mut self.variant)),
+            variant_names: VariantNames::AsIs,
+            lenient_variants: false,
+        })?;
         Ok((val, self))
     }
 }
@@ -514,10 +1043,12 @@ impl<'de> EnumAccess<'de> for Enum {
 impl<'de> VariantAccess<'de> for Enum {
     type Error = Error;
 
-    // If the `Visitor` expected this variant to be a unit variant, the input
-    // should have been the plain string case handled in `deserialize_enum`.
+    // Unit variants do not have a value
     fn unit_variant(self) -> Result<()> {
-        Err(Error::ExpectedUnitVariant)
+        match self.value {
+            None => Ok(()),
+            Some(_) => Err(ErrorKind::ExpectedUnitVariant.into()),
+        }
     }
 
     // Deserializes the variant value here
@@ -525,9 +1056,7 @@ impl<'de> VariantAccess<'de> for Enum {
     where
         T: DeserializeSeed<'de>,
     {
-        let (_, elem) = self.peek_next_elem()?;
-        let mut de = Deserializer::from_gura_type(elem);
-        seed.deserialize(&mut de)
+        seed.deserialize(&mut self.value_deserializer()?)
     }
 
     // Deserializes the sequence of data here
@@ -535,9 +1064,7 @@ impl<'de> VariantAccess<'de> for Enum {
     where
         V: Visitor<'de>,
     {
-        let (_, dequeue_as_vec) = self.peek_next_elem()?;
-        let de: &mut Deserializer = &mut Deserializer::from_gura_type(dequeue_as_vec);
-        de::Deserializer::deserialize_seq(de, visitor)
+        de::Deserializer::deserialize_seq(&mut self.value_deserializer()?, visitor)
     }
 
     // Deserializes the inner map here
@@ -545,9 +1072,7 @@ impl<'de> VariantAccess<'de> for Enum {
     where
         V: Visitor<'de>,
     {
-        let (_, dequeue_as_vec) = self.peek_next_elem()?;
-        let de: &mut Deserializer = &mut Deserializer::from_gura_type(dequeue_as_vec);
-        de::Deserializer::deserialize_map(de, visitor)
+        de::Deserializer::deserialize_map(&mut self.value_deserializer()?, visitor)
     }
 }
 
@@ -580,5 +1105,5 @@ pub fn from_value<T>(value: GuraType) -> Result<T>
 where
     T: DeserializeOwned,
 {
-    from_str(&value.to_string())
+    T::deserialize(&mut Deserializer::from_gura_type(value))
 }
@@ -0,0 +1,292 @@
+use crate::de::parse;
+use crate::dump::dump;
+use crate::error::Result;
+use gura::GuraType;
+use indexmap::IndexMap;
+use std::fmt::{self, Display};
+
+/// Options to customize how two Gura documents are compared.
+#[derive(Clone, Debug, Default)]
+pub struct DiffOptions {
+    array_key: Option<String>,
+}
+
+impl DiffOptions {
+    pub fn new() -> Self {
+        DiffOptions::default()
+    }
+
+    /// Matches elements of arrays of objects by the value of the given key field
+    /// instead of by their position. Arrays where some element is not an object,
+    /// lacks the field or repeats a value of it are still compared positionally.
+    pub fn array_key(mut self, key: &str) -> Self {
+        self.array_key = Some(key.to_string());
+        self
+    }
+}
+
+/// A single difference between two Gura documents.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    /// The path only exists in the new document
+    Added { path: String, value: GuraType },
+    /// The path only exists in the old document
+    Removed { path: String, value: GuraType },
+    /// The path exists in both documents with different values
+    Changed {
+        path: String,
+        old: GuraType,
+        new: GuraType,
+    },
+}
+
+impl Change {
+    /// Path of the changed value. Object keys are separated by dots, array
+    /// elements are written as `[index]` or `[key = value]` when matched by key.
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+/// List of changes between two Gura documents. Its `Display` implementation
+/// renders a human-readable summary with one entry per change.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Diff {
+    changes: Vec<Change>,
+}
+
+impl Diff {
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    pub fn into_changes(self) -> Vec<Change> {
+        self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for change in &self.changes {
+            match change {
+                Change::Added { path, value } => write_entry(f, '+', path, value)?,
+                Change::Removed { path, value } => write_entry(f, '-', path, value)?,
+                Change::Changed { path, old, new } => {
+                    let (old_str, new_str) = (dump(old), dump(new));
+                    if is_block(old, &old_str) || is_block(new, &new_str) {
+                        write_entry(f, '-', path, old)?;
+                        write_entry(f, '+', path, new)?;
+                    } else {
+                        writeln!(f, "~ {}: {} -> {}", display_path(path), old_str, new_str)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() {
+        "(root)"
+    } else {
+        path
+    }
+}
+
+/// Non empty objects and multiline values are rendered in an indented block
+/// below their path.
+fn is_block(value: &GuraType, dumped: &str) -> bool {
+    match value {
+        GuraType::Object(obj) => !obj.is_empty(),
+        _ => dumped.contains('\n'),
+    }
+}
+
+fn write_entry(f: &mut fmt::Formatter, sign: char, path: &str, value: &GuraType) -> fmt::Result {
+    let dumped = dump(value);
+    if is_block(value, &dumped) {
+        writeln!(f, "{} {}:", sign, display_path(path))?;
+        for line in dumped.lines() {
+            writeln!(f, "{}     {}", sign, line)?;
+        }
+        Ok(())
+    } else {
+        writeln!(f, "{} {}: {}", sign, display_path(path), dumped)
+    }
+}
+
+/// Compares two Gura documents and returns the changes between them. Formatting
+/// and comments are ignored as both documents are parsed before comparing them.
+///
+/// # Example
+///
+/// ```
+/// let old = r#"
+/// database:
+///     ip: "127.0.0.1"
+///     port: [80, 8080]
+/// "#;
+/// let new = r#"
+/// ## Production database
+/// database:
+///     ip: "127.0.0.1"
+///     port: [80, 8081]
+///     enabled: true
+/// "#;
+///
+/// let diff = serde_gura::diff(old, new).unwrap();
+/// assert_eq!(
+///     diff.to_string(),
+///     "~ database.port[1]: 8080 -> 8081\n+ database.enabled: true\n"
+/// );
+/// ```
+pub fn diff(old: &str, new: &str) -> Result<Diff> {
+    diff_with_options(old, new, &DiffOptions::default())
+}
+
+/// Same as `diff` but with custom comparison options.
+pub fn diff_with_options(old: &str, new: &str, options: &DiffOptions) -> Result<Diff> {
+    let old = parse(old)?;
+    let new = parse(new)?;
+    Ok(diff_values(&old, &new, options))
+}
+
+/// Compares two already parsed `gura::GuraType` values.
+pub fn diff_values(old: &GuraType, new: &GuraType, options: &DiffOptions) -> Diff {
+    let mut changes = Vec::new();
+    diff_into(&mut changes, String::new(), old, new, options);
+    Diff { changes }
+}
+
+fn diff_into(
+    changes: &mut Vec<Change>,
+    path: String,
+    old: &GuraType,
+    new: &GuraType,
+    options: &DiffOptions,
+) {
+    match (old, new) {
+        (GuraType::Object(old_obj), GuraType::Object(new_obj)) => {
+            for (key, old_value) in old_obj {
+                let child_path = join_key(&path, key);
+                match new_obj.get(key) {
+                    Some(new_value) => {
+                        diff_into(changes, child_path, old_value, new_value, options)
+                    }
+                    None => changes.push(Change::Removed {
+                        path: child_path,
+                        value: old_value.clone(),
+                    }),
+                }
+            }
+
+            for (key, new_value) in new_obj {
+                if !old_obj.contains_key(key) {
+                    changes.push(Change::Added {
+                        path: join_key(&path, key),
+                        value: new_value.clone(),
+                    });
+                }
+            }
+        }
+        (GuraType::Array(old_arr), GuraType::Array(new_arr)) => {
+            let keyed = options.array_key.as_deref().and_then(|key| {
+                Some((
+                    key,
+                    index_by_key(old_arr, key)?,
+                    index_by_key(new_arr, key)?,
+                ))
+            });
+
+            match keyed {
+                Some((key, old_idx, new_idx)) => {
+                    for (id, old_value) in &old_idx {
+                        let child_path = format!("{}[{} = {}]", path, key, id);
+                        match new_idx.get(id) {
+                            Some(new_value) => {
+                                diff_into(changes, child_path, old_value, new_value, options)
+                            }
+                            None => changes.push(Change::Removed {
+                                path: child_path,
+                                value: (*old_value).clone(),
+                            }),
+                        }
+                    }
+
+                    for (id, new_value) in &new_idx {
+                        if !old_idx.contains_key(id) {
+                            changes.push(Change::Added {
+                                path: format!("{}[{} = {}]", path, key, id),
+                                value: (*new_value).clone(),
+                            });
+                        }
+                    }
+                }
+                None => {
+                    for (idx, old_value) in old_arr.iter().enumerate() {
+                        let child_path = format!("{}[{}]", path, idx);
+                        match new_arr.get(idx) {
+                            Some(new_value) => {
+                                diff_into(changes, child_path, old_value, new_value, options)
+                            }
+                            None => changes.push(Change::Removed {
+                                path: child_path,
+                                value: old_value.clone(),
+                            }),
+                        }
+                    }
+
+                    for (idx, new_value) in new_arr.iter().enumerate().skip(old_arr.len()) {
+                        changes.push(Change::Added {
+                            path: format!("{}[{}]", path, idx),
+                            value: new_value.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        _ => {
+            if old != new {
+                changes.push(Change::Changed {
+                    path,
+                    old: old.clone(),
+                    new: new.clone(),
+                });
+            }
+        }
+    }
+}
+
+fn join_key(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Indexes an array of objects by the dumped value of `key`. Returns `None` if
+/// some element can not be identified unambiguously.
+fn index_by_key<'a>(array: &'a [GuraType], key: &str) -> Option<IndexMap<String, &'a GuraType>> {
+    let mut index = IndexMap::with_capacity(array.len());
+    for elem in array {
+        let id = match elem {
+            GuraType::Object(obj) => dump(obj.get(key)?),
+            _ => return None,
+        };
+
+        if index.insert(id, elem).is_some() {
+            return None;
+        }
+    }
+    Some(index)
+}
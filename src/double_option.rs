@@ -0,0 +1,60 @@
+//! Serialize and deserialize `Option<Option<T>>` fields telling apart a key
+//! that is not set from a key explicitly set to `null`:
+//!
+//! - `None`: the key is not in the document.
+//! - `Some(None)`: the key is set to `null`.
+//! - `Some(Some(value))`: the key is set to `value`.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, PartialEq, Debug)]
+//! struct Patch {
+//!     #[serde(
+//!         default,
+//!         skip_serializing_if = "Option::is_none",
+//!         with = "serde_gura::double_option"
+//!     )]
+//!     comment: Option<Option<String>>,
+//! }
+//!
+//! let unset: Patch = serde_gura::from_str("").unwrap();
+//! assert_eq!(unset, Patch { comment: None });
+//!
+//! let null: Patch = serde_gura::from_str("comment: null").unwrap();
+//! assert_eq!(null, Patch { comment: Some(None) });
+//! assert_eq!(serde_gura::to_string(&null).unwrap(), "comment: null");
+//!
+//! let set: Patch = serde_gura::from_str("comment: \"Hi\"").unwrap();
+//! assert_eq!(set, Patch { comment: Some(Some("Hi".to_string())) });
+//! ```
+//!
+//! `skip_serializing_if` is not needed if the fields are serialized with
+//! `Serializer::skip_none_fields` enabled. `default` is always required so a
+//! missing key is read as `None`.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub fn serialize<T, S>(value: &Option<Option<T>>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    match value {
+        None => serializer.serialize_none(),
+        // Written as `null` but not skipped by `skip_none_fields`
+        Some(None) => serializer.serialize_unit(),
+        Some(Some(value)) => value.serialize(serializer),
+    }
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    // The key is present, so even `null` is `Some`
+    Option::<T>::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,145 @@
+use gura::GuraType;
+use std::fmt::Write;
+
+// Indentation of 4 spaces
+pub(crate) const INDENT: &str = "    ";
+
+/// Generates a Gura string from a `gura::GuraType`. The output is the same as
+/// `gura::dump` except for floats, which are written with the shortest
+/// representation that parses back to the same value.
+///
+/// # Example
+///
+/// ```
+/// let value = gura::parse("ratio: 0.30000000000000004\noffset: -0.0").unwrap();
+/// assert_eq!(
+///     serde_gura::dump(&value),
+///     "ratio: 0.30000000000000004\noffset: -0.0"
+/// );
+/// ```
+pub fn dump(content: &GuraType) -> String {
+    dump_content(content).trim().to_string()
+}
+
+/// Writes a float so it parses back to the same `f64` and is always read as a
+/// float: integral values keep their fractional part (`1.0`), the sign of
+/// negative zero is kept and special values use Gura's `nan`, `inf` and `-inf`.
+pub(crate) fn format_float(value: f64) -> String {
+    let mut result = String::new();
+    write_float(&mut result, value);
+    result
+}
+
+/// Same as `format_float` but appending the float to `out`
+pub(crate) fn write_float(out: &mut String, value: f64) {
+    if value.is_nan() {
+        out.push_str("nan");
+    } else if value.is_infinite() {
+        if value.is_sign_positive() {
+            out.push_str("inf");
+        } else {
+            out.push_str("-inf");
+        }
+    } else {
+        // Debug formatting is the shortest representation that round-trips and
+        // always includes a fractional part or an exponent
+        let _ = write!(out, "{:?}", value);
+    }
+}
+
+pub(crate) fn escape_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    write_string(&mut result, value);
+    result
+}
+
+/// Same as `escape_string` but appending the quoted string to `out`
+pub(crate) fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '\x08' => out.push_str("\\b"),
+            '\x0c' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn dump_content(content: &GuraType) -> String {
+    match content {
+        GuraType::Null => "null".to_string(),
+        GuraType::String(str_content) => escape_string(str_content),
+        GuraType::Integer(number) => number.to_string(),
+        GuraType::BigInteger(number) => number.to_string(),
+        GuraType::Float(number) => format_float(*number),
+        GuraType::Bool(bool_value) => bool_value.to_string(),
+        GuraType::Object(values) => {
+            if values.is_empty() {
+                return "empty".to_string();
+            }
+
+            let mut result = String::new();
+            for (key, gura_value) in values.iter() {
+                let _ = write!(result, "{}:", key);
+
+                // If the value is an object, splits the stringified value by
+                // newline and indents each line before adding it to the result
+                match gura_value {
+                    GuraType::Object(obj) if !obj.is_empty() => {
+                        result.push('\n');
+                        for line in dump_content(gura_value).trim_end().split('\n') {
+                            let _ = writeln!(result, "{}{}", INDENT, line);
+                        }
+                    }
+                    _ => {
+                        let _ = writeln!(result, " {}", dump_content(gura_value));
+                    }
+                }
+            }
+
+            result
+        }
+        GuraType::Array(array) => {
+            // Arrays with objects are indented, arrays of primitive values or
+            // nested arrays are written in a single line
+            let should_multiline = array
+                .iter()
+                .any(|e| matches!(e, GuraType::Object(obj) if !obj.is_empty()));
+
+            if !should_multiline {
+                let values: Vec<String> = array.iter().map(dump_content).collect();
+                return format!("[{}]", values.join(", "));
+            }
+
+            let mut result = String::from("[");
+            let last_idx = array.len() - 1;
+
+            for (idx, elem) in array.iter().enumerate() {
+                result.push('\n');
+
+                let dumped = dump_content(elem);
+                let lines: Vec<String> = dumped
+                    .trim_end()
+                    .split('\n')
+                    .map(|line| format!("{}{}", INDENT, line))
+                    .collect();
+                result += &lines.join("\n");
+
+                // Add a comma if this entry is not the final entry in the list
+                if idx < last_idx {
+                    result.push(',');
+                }
+            }
+
+            result.push_str("\n]");
+            result
+        }
+        _ => String::new(),
+    }
+}
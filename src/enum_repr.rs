@@ -0,0 +1,140 @@
+use crate::error::{ErrorKind, Result};
+use std::borrow::Cow;
+
+/// How enum variants are represented in Gura. The same representation must be
+/// set on the `Serializer` and on the `Deserializer`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EnumRepr {
+    /// Unit variants are written as a string with the variant name, other
+    /// variants as an object with the variant name as its only key:
+    ///
+    /// ```text
+    /// level: "Info"
+    /// shape:
+    ///     Circle:
+    ///         radius: 1.5
+    /// ```
+    ///
+    /// Objects with more keys than the variant fail with
+    /// `ErrorKind::ExpectedEnum`, as the other keys would be lost.
+    #[default]
+    External,
+    /// Variants are written as an object with the variant name in the `tag`
+    /// field and its content, if any, in the `content` field:
+    ///
+    /// ```text
+    /// level:
+    ///     type: "Info"
+    /// shape:
+    ///     type: "Circle"
+    ///     value:
+    ///         radius: 1.5
+    /// ```
+    Adjacent {
+        tag: &'static str,
+        content: &'static str,
+    },
+}
+
+impl EnumRepr {
+    /// Adjacent representation with the `type` and `value` fields.
+    pub fn type_value() -> Self {
+        EnumRepr::Adjacent {
+            tag: "type",
+            content: "value",
+        }
+    }
+}
+
+/// How variant names are written. Names are converted from the Rust variant
+/// names, which are expected to be in `PascalCase`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VariantNames {
+    /// Variant names are kept as they are (`MaxValue`)
+    #[default]
+    AsIs,
+    /// `maxvalue`
+    Lowercase,
+    /// `max_value`
+    SnakeCase,
+    /// `max-value`. Keys can not contain dashes, so this only works for unit
+    /// variants with `EnumRepr::External` and for any variant with
+    /// `EnumRepr::Adjacent`.
+    KebabCase,
+}
+
+impl VariantNames {
+    /// Converts a Rust variant name into its written form
+    pub(crate) fn apply(self, variant: &str) -> Cow<'_, str> {
+        match self {
+            VariantNames::AsIs => Cow::Borrowed(variant),
+            VariantNames::Lowercase => Cow::Owned(variant.to_lowercase()),
+            VariantNames::SnakeCase => Cow::Owned(separate_words(variant, '_')),
+            VariantNames::KebabCase => Cow::Owned(separate_words(variant, '-')),
+        }
+    }
+
+    /// Finds the Rust variant written as `name`. If `lenient` is enabled and
+    /// no variant is written exactly as `name`, variants are compared ignoring
+    /// case, underscores and dashes.
+    pub(crate) fn resolve<'a>(
+        self,
+        name: &'a str,
+        variants: &'static [&'static str],
+        lenient: bool,
+    ) -> Result<&'a str> {
+        if let Some(variant) = variants.iter().find(|variant| self.apply(variant) == name) {
+            return Ok(variant);
+        }
+
+        if lenient {
+            let normalized = normalize(name);
+            let mut matches = variants
+                .iter()
+                .filter(|variant| normalize(variant) == normalized);
+            match (matches.next(), matches.next()) {
+                (Some(variant), None) => return Ok(variant),
+                (Some(_), Some(_)) => {
+                    return Err(ErrorKind::AmbiguousVariant(name.to_string()).into())
+                }
+                _ => {}
+            }
+        }
+
+        if self == VariantNames::AsIs {
+            // Serde reports the unknown variant
+            return Ok(name);
+        }
+
+        let expected: Vec<String> = variants
+            .iter()
+            .map(|variant| format!("`{}`", self.apply(variant)))
+            .collect();
+        Err(ErrorKind::Message(format!(
+            "unknown variant `{}`, expected one of {}",
+            name,
+            expected.join(", ")
+        ))
+        .into())
+    }
+}
+
+/// Lowercase name without word separators, so `MaxValue`, `maxValue`,
+/// `max_value`, `max-value` and `MAX_VALUE` are the same name
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn separate_words(variant: &str, separator: char) -> String {
+    let mut result = String::with_capacity(variant.len() + 4);
+    for (idx, c) in variant.char_indices() {
+        if c.is_uppercase() && idx > 0 {
+            result.push(separator);
+        }
+        result.extend(c.to_lowercase());
+    }
+    result
+}
@@ -1,61 +1,235 @@
+use gura::errors::{Error as GuraErrorKind, GuraError};
 use serde::{de, ser};
+use std::convert::TryFrom;
 use std::fmt::{self, Display};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-/// Types of errors that may occur during serialization/deserialization
+/// Error that may occur during serialization/deserialization. What went wrong
+/// is told by `Error::kind`, and `Error::classify` groups it into a broad
+/// `Category`. Errors found while reading the text also tell where, see
+/// `Error::line` and `Error::column`.
+#[derive(Clone, Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+/// Types of errors that may occur during serialization/deserialization. New
+/// kinds may be added in minor releases.
 #[derive(Clone, Debug, PartialEq)]
-pub enum Error {
-    // One or more variants that can be created by data structures through the
-    // `ser::Error` and `de::Error` traits
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Error created by data structures through the `ser::Error` and
+    /// `de::Error` traits
     Message(String),
 
+    /// Imported file that could not be read
+    Io(String),
+    /// Text that is not valid Gura
+    Syntax(String),
+
     /// Invalid GuraType
     InvalidType,
     ExpectedIdentifier,
-
-    // Zero or more variants that can be created directly by the Serializer and
-    // Deserializer without going through `ser::Error` and `de::Error`
-    Eof,
-    Syntax(String),
     ExpectedBytes,
     ExpectedBoolean,
     ExpectedInteger,
     ExpectedFloat,
+    /// Integer that can not be converted to a float without losing precision
+    InexactFloat(String),
     ExpectedChar,
     ExpectedString,
     ExpectedNull,
     ExpectedArray,
-    ExpectedArrayComma,
-    ExpectedArrayEnd,
     ExpectedMap,
-    ExpectedMapColon,
-    ExpectedMapComma,
-    ExpectedMapEnd,
+    /// A map value was serialized before its key
+    ExpectedMapKey,
+    /// A map key was serialized without its value
+    ExpectedMapValue,
     ExpectedEnum,
-    UnitNotSupported,
-    /// Empty values are not valid in Gura
-    ExpectedObjectValue,
-    TrailingCharacters,
     /// Enums errors
     ExpectedUnitVariant,
+    /// A newtype, tuple or struct variant was written without its value
+    ExpectedVariantValue,
+    /// Variant name that matches more than one variant leniently
+    AmbiguousVariant(String),
+    /// Key that can not be written as a Gura key
+    InvalidKey(String),
+    /// Key repeated in the same object
+    DuplicateKey(String),
+    /// Map key that is not a string, integer, char, bool or unit variant
+    KeyMustBeAString,
+    /// Path to a value that is not in the document
+    MissingPath(String),
+
+    /// Arrays and objects nested deeper than `Limits::max_depth` or the
+    /// `max_depth` of a `Serializer`
+    DepthLimit(usize),
+    /// More values than `Limits::max_nodes`
+    NodeLimit(usize),
+    /// String or key longer than `Limits::max_string_length`
+    StringLengthLimit(usize),
+    /// Text longer than `Limits::max_input_size`
+    InputSizeLimit(usize),
+}
+
+/// Broad category of an `Error`, returned by `Error::classify`. New categories
+/// may be added in minor releases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Category {
+    /// An imported file could not be read
+    Io,
+    /// The text is not valid Gura
+    Syntax,
+    /// The data does not match the type it is read into or written from
+    Data,
+    /// The data exceeds the `Limits` of the deserializer or the limits set on
+    /// the `Serializer`
+    Limit,
+}
+
+impl Error {
+    /// What went wrong
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn into_kind(self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Line of the text where the error was found, starting at 1. Known for
+    /// syntax errors and for the limits checked by `TextDeserializer` while
+    /// reading
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let err = serde_gura::from_str::<serde_json::Value>("a: 1\nb: [1, 2 3]").unwrap_err();
+    /// assert_eq!((err.line(), err.column()), (Some(2), Some(10)));
+    /// ```
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// Column of the text where the error was found, in characters and
+    /// starting at 1. See `Error::line`
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+
+    /// Sets where the error was found
+    pub(crate) fn at(mut self, line: usize, column: usize) -> Self {
+        self.line = Some(line);
+        self.column = Some(column);
+        self
+    }
+
+    /// Same as `From<GuraError>`, finding the column of the error in the text
+    /// that was parsed, as `gura` only gives its line and offset
+    pub(crate) fn from_gura(err: GuraError, text: &str) -> Self {
+        let pos = usize::try_from(err.pos).ok();
+        let mut error = Error::from(err);
+        if let (Some(pos), Some(line)) = (pos, error.line) {
+            let line_start = if line == 1 {
+                Some(0)
+            } else {
+                text.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '\n')
+                    .nth(line - 2)
+                    .map(|(idx, _)| idx + 1)
+            };
+            error.column = line_start
+                .and_then(|start| pos.checked_sub(start))
+                .map(|column| column + 1);
+        }
+        error
+    }
+
+    /// Groups the error into a broad category, so callers can react to the
+    /// kind of failure without matching every `ErrorKind`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use serde_gura::Category;
+    ///
+    /// let err = serde_gura::from_str::<u8>("a: 1\na: 2").unwrap_err();
+    /// assert_eq!(err.classify(), Category::Syntax);
+    /// let err = serde_gura::from_str::<u8>("a: 1").unwrap_err();
+    /// assert_eq!(err.classify(), Category::Data);
+    /// ```
+    pub fn classify(&self) -> Category {
+        use ErrorKind::*;
+
+        match self.kind {
+            Io(_) => Category::Io,
+            Syntax(_) => Category::Syntax,
+            DepthLimit(_) | NodeLimit(_) | StringLengthLimit(_) | InputSizeLimit(_) => {
+                Category::Limit
+            }
+            _ => Category::Data,
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error {
+            kind,
+            line: None,
+            column: None,
+        }
+    }
+}
+
+/// Imported files that are not found are I/O errors, the rest are syntax
+/// errors. Only the line is kept, see `Error::from_gura`
+impl From<GuraError> for Error {
+    fn from(err: GuraError) -> Self {
+        let kind = match err.kind {
+            GuraErrorKind::FileNotFoundError => ErrorKind::Io(err.msg),
+            _ => ErrorKind::Syntax(err.msg),
+        };
+        Error {
+            kind,
+            // `gura` does not know the line of some errors, like missing imports
+            line: Some(err.line).filter(|line| *line > 0),
+            column: None,
+        }
+    }
 }
 
 impl ser::Error for Error {
     fn custom<T: Display>(msg: T) -> Self {
-        Error::Message(msg.to_string())
+        ErrorKind::Message(msg.to_string()).into()
     }
 }
 
 impl de::Error for Error {
     fn custom<T: Display>(msg: T) -> Self {
-        Error::Message(msg.to_string())
+        ErrorKind::Message(msg.to_string()).into()
     }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use Error::*;
+        self.kind.fmt(f)?;
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, " at line {} column {}", line, column),
+            (Some(line), None) => write!(f, " at line {}", line),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ErrorKind::*;
 
         match self {
             Syntax(msg) => write!(
@@ -63,9 +237,8 @@ impl Display for Error {
                 "Input text does not have a valid Gura format. Parsing failed with error \"{}\"",
                 msg
             ),
+            Io(msg) => write!(f, "Imported file could not be read: {}", msg),
             Message(msg) => write!(f, "{}", msg),
-            Eof => f.write_str("Unexpected end of input"),
-            UnitNotSupported => f.write_str("Unit values are not supported in Gura"),
 
             ExpectedBytes => f.write_str("Expected byte sequence"),
             ExpectedBoolean => f.write_str("Expected boolean"),
@@ -75,28 +248,48 @@ impl Display for Error {
                 " (No implicit coversion between int and float, ",
                 "see https://gura.netlify.app/docs/spec#float)"
             )),
+            InexactFloat(int) => write!(
+                f,
+                "Integer {} can not be converted to a float without losing precision",
+                int
+            ),
             ExpectedChar => f.write_str("Expected char"),
             ExpectedString => f.write_str("Expected string"),
             ExpectedNull => f.write_str("Expected null value"),
             ExpectedArray => f.write_str("Expected array"),
-            ExpectedArrayEnd => f.write_str("Expected array end"),
 
             ExpectedMap => f.write_str("Expected map"),
-            ExpectedMapColon => f.write_str("Expected colon at map"),
-            ExpectedMapComma => f.write_str("Expected comma at map"),
-            ExpectedMapEnd => f.write_str("Expected map end"),
+            ExpectedMapKey => f.write_str("Expected map key before its value"),
+            ExpectedMapValue => f.write_str("Expected map value after its key"),
 
             ExpectedEnum => f.write_str("Expected enum value"),
 
-            ExpectedObjectValue => f.write_str("Expected not empty object block"),
-            TrailingCharacters => f.write_str("Invalid trailing characters"),
-
             ExpectedUnitVariant => f.write_str("Expected unit variant at enum"),
-
-            ExpectedArrayComma => f.write_str("Expected comma at array"),
+            ExpectedVariantValue => f.write_str("Expected value for enum variant"),
+            AmbiguousVariant(name) => write!(
+                f,
+                "Ambiguous enum variant \"{}\": it matches more than one variant",
+                name
+            ),
 
             InvalidType => f.write_str("Invalid type"),
             ExpectedIdentifier => f.write_str("Expected identifier"),
+            InvalidKey(key) => write!(
+                f,
+                "Invalid key \"{}\": Gura keys can only contain letters, digits and underscores",
+                key
+            ),
+            DuplicateKey(key) => write!(f, "Duplicate key \"{}\"", key),
+            KeyMustBeAString => {
+                f.write_str("Map keys must be strings, integers, chars, bools or unit variants")
+            }
+            MissingPath(path) => write!(f, "There is no value at \"{}\"", path),
+            DepthLimit(max) => write!(f, "Values are nested deeper than the limit of {}", max),
+            NodeLimit(max) => write!(f, "Document has more values than the limit of {}", max),
+            StringLengthLimit(max) => {
+                write!(f, "String is longer than the limit of {} bytes", max)
+            }
+            InputSizeLimit(max) => write!(f, "Input is larger than the limit of {} bytes", max),
         }
     }
 }
@@ -0,0 +1,172 @@
+//! Conversions between JSON and Gura. Requires the `json` feature.
+
+use crate::de::parse;
+use crate::error::{Error, ErrorKind, Result};
+use crate::transcode::{check_representable, to_document, transcode_from, transcode_value_into};
+use gura::GuraType;
+use std::convert::TryFrom;
+
+/// Converts a JSON document into a Gura document.
+///
+/// Integers bigger than `u64::MAX` are read as floats by `serde_json`. The root
+/// must be an object, and an empty object is converted into an empty document.
+///
+/// # Example
+///
+/// ```
+/// let gura = serde_gura::json::to_gura(r#"{"ip": "127.0.0.1", "port": [80, 8080]}"#).unwrap();
+/// assert_eq!(gura, "ip: \"127.0.0.1\"\nport: [80, 8080]");
+/// ```
+pub fn to_gura(json: &str) -> Result<String> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let value = transcode_from(&mut deserializer)?;
+    deserializer
+        .end()
+        .map_err(|e| ErrorKind::Syntax(e.to_string()))?;
+    to_document(&value)
+}
+
+/// Converts a Gura document into a pretty printed JSON document.
+///
+/// Fails if the document contains `nan` or infinite floats, which have no JSON
+/// representation.
+pub fn from_gura(gura: &str) -> Result<String> {
+    let value = parse(gura)?;
+    check_representable(&value, "JSON", |elem| match elem {
+        GuraType::Float(float) if !float.is_finite() => Some(format!("Float `{}`", float)),
+        _ => None,
+    })?;
+
+    let mut buffer = Vec::new();
+    let mut serializer = serde_json::Serializer::pretty(&mut buffer);
+    transcode_value_into(value, &mut serializer)?;
+    String::from_utf8(buffer).map_err(|e| ErrorKind::Message(e.to_string()).into())
+}
+
+/// Gura value that converts from and into a `serde_json::Value` with `From`
+/// and `TryFrom`, which can not be implemented for `gura::GuraType` itself as
+/// both types belong to other crates.
+///
+/// Integers keep being integers and floats keep being floats. JSON integers
+/// become `GuraType::Integer`, or `GuraType::BigInteger` if they do not fit in
+/// an `isize`. Converting into JSON fails for `nan` and infinite floats and for
+/// big integers that do not fit in an `i64` or `u64`, as `serde_json` can not
+/// represent them.
+///
+/// # Example
+///
+/// ```
+/// use serde_gura::json::GuraValue;
+/// use std::convert::TryFrom;
+///
+/// let value = GuraValue(gura::parse("port: 8080\nratio: 1.0").unwrap());
+/// let json = serde_json::Value::try_from(&value).unwrap();
+/// assert_eq!(json, serde_json::json!({"port": 8080, "ratio": 1.0}));
+/// assert!(json["ratio"].is_f64());
+/// assert_eq!(GuraValue::from(json), value);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct GuraValue(pub GuraType);
+
+impl From<GuraType> for GuraValue {
+    fn from(value: GuraType) -> Self {
+        GuraValue(value)
+    }
+}
+
+impl From<GuraValue> for GuraType {
+    fn from(value: GuraValue) -> Self {
+        value.0
+    }
+}
+
+impl From<serde_json::Value> for GuraValue {
+    fn from(value: serde_json::Value) -> Self {
+        GuraValue(from_json_value(value))
+    }
+}
+
+impl TryFrom<&GuraValue> for serde_json::Value {
+    type Error = Error;
+
+    fn try_from(value: &GuraValue) -> Result<Self> {
+        to_json_value(&value.0)
+    }
+}
+
+impl TryFrom<GuraValue> for serde_json::Value {
+    type Error = Error;
+
+    fn try_from(value: GuraValue) -> Result<Self> {
+        to_json_value(&value.0)
+    }
+}
+
+fn to_json_value(value: &GuraType) -> Result<serde_json::Value> {
+    use serde_json::Value;
+
+    Ok(match value {
+        GuraType::Null => Value::Null,
+        GuraType::Bool(boolean) => Value::Bool(*boolean),
+        GuraType::String(str) => Value::String(str.clone()),
+        GuraType::Integer(int) => Value::from(*int as i64),
+        GuraType::BigInteger(int) => {
+            if let Ok(int) = i64::try_from(*int) {
+                Value::from(int)
+            } else if let Ok(int) = u64::try_from(*int) {
+                Value::from(int)
+            } else {
+                return Err(ErrorKind::Message(format!(
+                    "Integer `{}` can not be represented in JSON",
+                    int
+                ))
+                .into());
+            }
+        }
+        GuraType::Float(float) => serde_json::Number::from_f64(*float)
+            .map(Value::Number)
+            .ok_or_else(|| {
+                ErrorKind::Message(format!("Float `{}` can not be represented in JSON", float))
+            })?,
+        GuraType::Array(array) => Value::Array(
+            array
+                .iter()
+                .map(to_json_value)
+                .collect::<Result<Vec<Value>>>()?,
+        ),
+        GuraType::Object(obj) => Value::Object(
+            obj.iter()
+                .map(|(key, elem)| Ok((key.clone(), to_json_value(elem)?)))
+                .collect::<Result<serde_json::Map<String, Value>>>()?,
+        ),
+        _ => return Err(ErrorKind::InvalidType.into()),
+    })
+}
+
+fn from_json_value(value: serde_json::Value) -> GuraType {
+    use serde_json::Value;
+
+    match value {
+        Value::Null => GuraType::Null,
+        Value::Bool(boolean) => GuraType::Bool(boolean),
+        Value::Number(number) => {
+            if let Some(int) = number.as_i64().and_then(|int| isize::try_from(int).ok()) {
+                GuraType::Integer(int)
+            } else if let Some(int) = number.as_i64() {
+                GuraType::BigInteger(int as i128)
+            } else if let Some(int) = number.as_u64() {
+                GuraType::BigInteger(int as i128)
+            } else {
+                // Without `arbitrary_precision` every other number is a finite f64
+                GuraType::Float(number.as_f64().unwrap_or_default())
+            }
+        }
+        Value::String(str) => GuraType::String(str),
+        Value::Array(array) => GuraType::Array(array.into_iter().map(from_json_value).collect()),
+        Value::Object(obj) => GuraType::Object(
+            obj.into_iter()
+                .map(|(key, elem)| (key, from_json_value(elem)))
+                .collect(),
+        ),
+    }
+}
@@ -54,9 +54,35 @@
 //! ```
 
 mod de;
+mod diff;
+pub mod double_option;
+mod dump;
+mod enum_repr;
 mod error;
+mod parser;
 mod ser;
+mod stream;
+mod text;
+#[cfg(feature = "transcode")]
+mod transcode;
 
-pub use crate::de::{from_str, Deserializer, from_value};
-pub use crate::error::{Error, Result};
-pub use crate::ser::{to_string, Serializer, to_value};
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "toml")]
+pub mod toml;
+#[cfg(feature = "yaml")]
+pub mod yaml;
+
+pub use crate::de::{from_str, from_str_with_limits, from_value, Deserializer, Limits};
+pub use crate::diff::{diff, diff_values, diff_with_options, Change, Diff, DiffOptions};
+pub use crate::dump::dump;
+pub use crate::enum_repr::{EnumRepr, VariantNames};
+pub use crate::error::{Category, Error, ErrorKind, Result};
+pub use crate::ser::{to_string, to_value, Serializer};
+pub use crate::stream::{
+    from_str_direct, iter_array, iter_entries, ArrayElements, DuplicateKeys, Entries,
+    TextDeserializer,
+};
+pub use crate::text::{to_string_direct, TextSerializer};
+#[cfg(feature = "transcode")]
+pub use crate::transcode::{transcode_from, transcode_into};
@@ -0,0 +1,947 @@
+//! Gura parser that reads the text on demand, so values can be handed to serde
+//! as they are found instead of building a `GuraType` tree first.
+//!
+//! The grammar follows the one of the `gura` crate, including how indentation
+//! levels are tracked, so both parsers accept the same documents and read the
+//! same values from them. Imports are not supported.
+
+use crate::de::Limits;
+use crate::error::{Error, ErrorKind, Result};
+use gura::GuraType;
+use indexmap::IndexMap;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::env;
+
+/// Primitive value
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Scalar<'de> {
+    Null,
+    Bool(bool),
+    Integer(isize),
+    BigInteger(i128),
+    Float(f64),
+    /// Borrowed from the text unless it has escapes or variables
+    String(Cow<'de, str>),
+    /// The `empty` keyword
+    Empty,
+}
+
+impl Scalar<'_> {
+    pub(crate) fn into_gura_type(self) -> GuraType {
+        match self {
+            Scalar::Null => GuraType::Null,
+            Scalar::Bool(value) => GuraType::Bool(value),
+            Scalar::Integer(value) => GuraType::Integer(value),
+            Scalar::BigInteger(value) => GuraType::BigInteger(value),
+            Scalar::Float(value) => GuraType::Float(value),
+            Scalar::String(value) => GuraType::String(value.into_owned()),
+            Scalar::Empty => GuraType::Object(IndexMap::new()),
+        }
+    }
+}
+
+/// Start of a value
+#[derive(Debug, PartialEq)]
+pub(crate) enum Token<'de> {
+    Scalar(Scalar<'de>),
+    /// The `[` was read, elements are read with `Parser::next_element`
+    Array,
+    /// Entries are read with `Parser::next_key`
+    Object,
+}
+
+/// Value read last, checked by the pair that contains it
+#[derive(Clone, Copy, Debug)]
+enum Completed {
+    Scalar,
+    Array,
+    /// Indentation of the last pair of the object, if it had any
+    Object(Option<usize>),
+}
+
+/// Variables can only hold strings and numbers
+#[derive(Clone, Debug)]
+enum Variable<'de> {
+    String(Cow<'de, str>),
+    Integer(isize),
+    Float(f64),
+}
+
+/// Line read inside an object
+enum Line<'de> {
+    /// `key:`, with the indentation of the line and the offset of the key
+    Pair(&'de str, usize, usize),
+    /// Pair with less indentation, which belongs to a parent object
+    Break,
+    /// Variable definition, comment or blank line
+    Other,
+}
+
+/// Index of the last pair of each key of the objects of a document, by the
+/// offset where each object starts
+pub(crate) type LastPairs<'de> = HashMap<usize, HashMap<&'de str, usize>>;
+
+/// Object being read
+#[derive(Debug, Default)]
+pub(crate) struct ObjectState<'de> {
+    /// Offset of the first pair of each key
+    keys: HashMap<&'de str, usize>,
+    /// Key and indentation of the pair whose value is being read
+    pair: Option<(&'de str, usize)>,
+    /// Offsets of the current pair and of the first one with the same key, if
+    /// the key is repeated
+    repeated: Option<(usize, usize)>,
+    /// Number of pairs read
+    pairs: usize,
+    /// Indentation of the last pair
+    level: Option<usize>,
+    done: bool,
+    /// Whether the end of the object was counted in the depth of the parser
+    ended: bool,
+    /// Index of the last pair of each key, if they were looked ahead
+    last_pairs: Option<HashMap<&'de str, usize>>,
+}
+
+impl<'de> ObjectState<'de> {
+    /// Object whose repeated keys are overridden by their last pair
+    pub(crate) fn with_last_pairs(last_pairs: HashMap<&'de str, usize>) -> Self {
+        ObjectState {
+            last_pairs: Some(last_pairs),
+            ..ObjectState::default()
+        }
+    }
+
+    /// Whether the key read last is defined again later in the object. Only
+    /// known for objects created with `with_last_pairs`
+    pub(crate) fn is_overridden(&self) -> bool {
+        match (&self.last_pairs, self.pair) {
+            (Some(last_pairs), Some((key, _))) => last_pairs[key] != self.pair_index(),
+            _ => false,
+        }
+    }
+
+    /// Whether a key was read but not its value
+    pub(crate) fn has_pair(&self) -> bool {
+        self.pair.is_some()
+    }
+
+    /// Whether the key read last was already defined in the object
+    pub(crate) fn is_repeated(&self) -> bool {
+        self.repeated.is_some()
+    }
+
+    /// Index of the pair read last
+    pub(crate) fn pair_index(&self) -> usize {
+        self.pairs - 1
+    }
+}
+
+/// Array being read
+#[derive(Debug, Default)]
+pub(crate) struct ArrayState {
+    started: bool,
+    done: bool,
+}
+
+#[derive(Clone)]
+pub(crate) struct Parser<'de> {
+    input: &'de str,
+    /// Byte offset of the next character
+    pos: usize,
+    indentation_levels: Vec<usize>,
+    variables: HashMap<&'de str, Variable<'de>>,
+    completed: Completed,
+    /// Number of values started, to find out whether a value was read
+    values: usize,
+    limits: Limits,
+    /// Arrays and objects started and not ended yet
+    depth: usize,
+    /// Whether import lines are skipped instead of rejected
+    skip_imports: bool,
+}
+
+fn is_key_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+fn is_number_char(c: u8) -> bool {
+    matches!(c,
+        b'0'..=b'9' | b'A'..=b'F' | b'a'..=b'f'
+        | b'x' | b'o' | b'i' | b'n' | b'+' | b'.' | b'_' | b'-')
+}
+
+impl<'de> Parser<'de> {
+    pub(crate) fn new(input: &'de str) -> Self {
+        Parser {
+            input,
+            pos: 0,
+            indentation_levels: Vec::new(),
+            variables: HashMap::new(),
+            completed: Completed::Scalar,
+            values: 0,
+            limits: Limits::default(),
+            depth: 0,
+            skip_imports: false,
+        }
+    }
+
+    /// Reads a whole document checking `limits`, without keeping its values.
+    /// Import lines are skipped, as imported files are only read by `gura`
+    pub(crate) fn check_limits(input: &'de str, limits: Limits) -> Result<()> {
+        let mut parser = Parser::new(input);
+        parser.limits = limits;
+        parser.skip_imports = true;
+        let token = parser.parse_root()?;
+        parser.skip(token)?;
+        parser.end()
+    }
+
+    /// Sets the limits checked while reading
+    pub(crate) fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Counts a value started at `pos`, checking the limits on it
+    fn start_value(&mut self, token: &Token<'de>, pos: usize) -> Result<()> {
+        self.check_value(token).map_err(|err| self.locate(err, pos))
+    }
+
+    fn check_value(&mut self, token: &Token<'de>) -> Result<()> {
+        self.values += 1;
+        self.limits.check_nodes(self.values)?;
+        match token {
+            Token::Array | Token::Object => {
+                self.depth += 1;
+                self.limits.check_depth(self.depth)
+            }
+            // `empty` is read as an object
+            Token::Scalar(Scalar::Empty) => self.limits.check_depth(self.depth + 1),
+            Token::Scalar(Scalar::String(str)) => self.limits.check_string(str),
+            Token::Scalar(_) => Ok(()),
+        }
+    }
+
+    /// Number of values started so far
+    pub(crate) fn values(&self) -> usize {
+        self.values
+    }
+
+    /// Byte offset of the next character
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Line and column of a byte offset, both starting at 1
+    pub(crate) fn position(&self, pos: usize) -> (usize, usize) {
+        let before = &self.input[..pos];
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        (
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+
+    fn error_at(&self, pos: usize, msg: String) -> Error {
+        self.locate(ErrorKind::Syntax(msg).into(), pos)
+    }
+
+    /// Sets the position of an error to a byte offset
+    fn locate(&self, err: Error, pos: usize) -> Error {
+        let (line, column) = self.position(pos);
+        err.at(line, column)
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        self.error_at(self.pos, msg.to_string())
+    }
+
+    fn unexpected(&self, expected: &str) -> Error {
+        let found = match self.rest().chars().next() {
+            Some(c) => format!("\"{}\"", c.escape_debug()),
+            None => "end of input".to_string(),
+        };
+        self.error_at(self.pos, format!("Expected {} but got {}", expected, found))
+    }
+
+    fn rest(&self) -> &'de str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn is_eof(&self) -> bool {
+        self.pos == self.input.len()
+    }
+
+    fn eat(&mut self, expected: &str) -> bool {
+        let found = self.rest().starts_with(expected);
+        if found {
+            self.pos += expected.len();
+        }
+        found
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.rest().chars().next()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    /// Blanks and tabs
+    fn eat_ws(&mut self) {
+        while let Some(b' ') | Some(b'\t') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    /// New lines are `\n`, `\r\n`, form feeds, vertical tabs and backspaces
+    fn eat_new_line(&mut self) -> bool {
+        let len = match self.peek() {
+            Some(b'\n') | Some(b'\x0c') | Some(b'\x0b') | Some(b'\x08') => 1,
+            Some(b'\r') if self.rest().starts_with("\r\n") => 2,
+            _ => return false,
+        };
+        self.pos += len;
+        true
+    }
+
+    fn eat_ws_and_new_lines(&mut self) {
+        while self.eat(" ") || self.eat_new_line() {}
+    }
+
+    /// Letters, digits and underscores. Might be empty
+    fn eat_key(&mut self) -> &'de str {
+        let rest = self.rest();
+        let len = rest.bytes().take_while(|c| is_key_char(*c)).count();
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Consumes a line with only blanks and an optional comment. The position
+    /// is kept if the line has anything else
+    fn useless_line(&mut self) -> bool {
+        let start = self.pos;
+        self.eat_ws();
+        if self.peek() == Some(b'#') {
+            let end = self.rest().find(['\n', '\r', '\x0c', '\x0b', '\x08']);
+            match end {
+                Some(end) => {
+                    self.pos += end;
+                    if !self.eat_new_line() {
+                        // A single `\r` ends comments too
+                        self.pos += 1;
+                    }
+                }
+                None => self.pos = self.input.len(),
+            }
+            self.eat_new_line();
+            true
+        } else if self.eat_new_line() || self.is_eof() {
+            true
+        } else {
+            self.pos = start;
+            false
+        }
+    }
+
+    /// Reads the variables and useless lines before the first pair of the
+    /// document
+    pub(crate) fn parse_root(&mut self) -> Result<Token<'de>> {
+        self.limits.check_input(self.input)?;
+        while !self.is_eof() {
+            if self.rest().starts_with("import ") {
+                if !self.skip_imports {
+                    return Err(self.error("Imports are only supported by `from_str`"));
+                }
+                self.pos = self
+                    .rest()
+                    .find('\n')
+                    .map_or(self.input.len(), |end| self.pos + end);
+                self.eat_new_line();
+                continue;
+            }
+            if self.peek() == Some(b'$') {
+                self.variable()?;
+            } else if !self.useless_line() {
+                break;
+            }
+        }
+
+        let token = if self.is_eof() {
+            Token::Scalar(Scalar::Empty)
+        } else {
+            Token::Object
+        };
+        self.start_value(&token, self.pos)?;
+        Ok(token)
+    }
+
+    /// Reads the start of the value of a pair or of an array element
+    pub(crate) fn parse_value(&mut self) -> Result<Token<'de>> {
+        let start = self.pos;
+        self.eat_ws();
+        let value_pos = self.pos;
+        let token = if let Some(scalar) = self.primitive()? {
+            self.eat_ws();
+            self.completed = Completed::Scalar;
+            Token::Scalar(scalar)
+        } else if self.eat("[") {
+            Token::Array
+        } else {
+            // Objects are read from the start, as the indentation of their
+            // first pair is checked
+            self.pos = start;
+            Token::Object
+        };
+        self.start_value(&token, value_pos)?;
+        Ok(token)
+    }
+
+    fn primitive(&mut self) -> Result<Option<Scalar<'de>>> {
+        let scalar = match self.peek() {
+            Some(b'"') => Scalar::String(self.basic_string()?),
+            Some(b'\'') => Scalar::String(self.literal_string()?),
+            Some(b'$') => match self.variable_value()? {
+                Variable::String(value) => Scalar::String(value),
+                Variable::Integer(value) => Scalar::Integer(value),
+                Variable::Float(value) => Scalar::Float(value),
+            },
+            _ if self.eat("null") => Scalar::Null,
+            _ if self.eat("true") => Scalar::Bool(true),
+            _ if self.eat("false") => Scalar::Bool(false),
+            _ => match self.number()? {
+                Some(number) => number,
+                None if self.eat("empty") => Scalar::Empty,
+                None => return Ok(None),
+            },
+        };
+        Ok(Some(scalar))
+    }
+
+    /// Reads a number. Returns `None` without consuming anything if the text is
+    /// not a number, as it might be the key of an object
+    fn number(&mut self) -> Result<Option<Scalar<'de>>> {
+        let rest = self.rest();
+        let len = rest.bytes().take_while(|c| is_number_char(*c)).count();
+        if len == 0 {
+            return Ok(None);
+        }
+
+        let text = &rest[..len];
+        // Like the `gura` crate, the first char does not make it a float
+        let is_float = text[1..].contains(['E', 'e', '.']);
+        let number = if text.contains('_') {
+            Cow::Owned(text.replace('_', ""))
+        } else {
+            Cow::Borrowed(text)
+        };
+
+        let radix = match number.get(..2) {
+            Some("0x") => Some(16),
+            Some("0o") => Some(8),
+            Some("0b") => Some(2),
+            _ => None,
+        };
+        let scalar = if let Some(radix) = radix {
+            match isize::from_str_radix(&number[2..], radix) {
+                Ok(value) => Scalar::Integer(value),
+                Err(_) => {
+                    return Err(
+                        self.error_at(self.pos, format!("\"{}\" is not a valid number", number))
+                    )
+                }
+            }
+        } else if number.ends_with("inf") {
+            if number.starts_with('-') {
+                Scalar::Float(f64::NEG_INFINITY)
+            } else {
+                Scalar::Float(f64::INFINITY)
+            }
+        } else if number.ends_with("nan") {
+            Scalar::Float(f64::NAN)
+        } else if is_float {
+            match number.parse() {
+                Ok(value) => Scalar::Float(value),
+                Err(_) => return Ok(None),
+            }
+        } else if let Ok(value) = number.parse() {
+            Scalar::Integer(value)
+        } else if let Ok(value) = number.parse() {
+            Scalar::BigInteger(value)
+        } else {
+            return Ok(None);
+        };
+
+        self.pos += len;
+        Ok(Some(scalar))
+    }
+
+    /// Reads a `"` or `"""` string, replacing escapes and variables
+    fn basic_string(&mut self) -> Result<Cow<'de, str>> {
+        let start = self.pos;
+        let quote = if self.rest().starts_with("\"\"\"") {
+            "\"\"\""
+        } else {
+            "\""
+        };
+        let is_multiline = quote.len() == 3;
+        self.pos += quote.len();
+        if is_multiline {
+            // A new line right after the quotes is not part of the string
+            self.eat_new_line();
+        }
+
+        let content_start = self.pos;
+        // Only allocated when the string has escapes or variables
+        let mut owned: Option<String> = None;
+        loop {
+            let content_end = self.pos;
+            if self.eat(quote) {
+                return Ok(match owned {
+                    Some(owned) => Cow::Owned(owned),
+                    None => Cow::Borrowed(&self.input[content_start..content_end]),
+                });
+            }
+
+            let c = match self.next_char() {
+                Some(c) => c,
+                None => {
+                    return Err(self.error_at(start, "Unterminated string".to_string()));
+                }
+            };
+            match c {
+                '\\' => {
+                    let value = owned
+                        .get_or_insert_with(|| self.input[content_start..content_end].to_string());
+                    let escape_start = self.pos;
+                    let escape = match self.rest().chars().next() {
+                        Some(escape) => escape,
+                        None => return Err(self.error_at(start, "Unterminated string".to_string())),
+                    };
+                    self.pos += escape.len_utf8();
+
+                    let crlf = escape == '\r' && self.eat("\n");
+                    if is_multiline && (escape == '\n' || crlf) {
+                        // A backslash at the end of a line trims the blanks
+                        // and new lines that follow
+                        self.eat_ws_and_new_lines();
+                        continue;
+                    }
+
+                    match escape {
+                        'u' | 'U' => {
+                            let len = if escape == 'u' { 4 } else { 8 };
+                            let code = self
+                                .rest()
+                                .get(..len)
+                                .filter(|code| code.bytes().all(|c| c.is_ascii_hexdigit()));
+                            let c = code
+                                .and_then(|code| u32::from_str_radix(code, 16).ok())
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| {
+                                    self.error_at(escape_start, "Bad hex value".to_string())
+                                })?;
+                            self.pos += len;
+                            value.push(c);
+                        }
+                        'b' => value.push('\x08'),
+                        'f' => value.push('\x0c'),
+                        'n' => value.push('\n'),
+                        'r' => value.push('\r'),
+                        't' => value.push('\t'),
+                        '"' | '\\' | '$' => value.push(escape),
+                        _ => {
+                            // Unknown escapes are kept as they are
+                            value.push('\\');
+                            value.push(escape);
+                            if crlf {
+                                value.push('\n');
+                            }
+                        }
+                    }
+                }
+                '$' => {
+                    let var_start = self.pos - 1;
+                    let value = owned
+                        .get_or_insert_with(|| self.input[content_start..content_end].to_string());
+                    let name = self.eat_key();
+                    match self.variable_named(name, var_start)? {
+                        Variable::String(var) => value.push_str(&var),
+                        Variable::Integer(var) => value.push_str(&var.to_string()),
+                        Variable::Float(var) => value.push_str(&var.to_string()),
+                    }
+                }
+                c => {
+                    if let Some(value) = &mut owned {
+                        value.push(c);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads a `'` or `'''` string, which has no escapes
+    fn literal_string(&mut self) -> Result<Cow<'de, str>> {
+        let start = self.pos;
+        let quote = if self.rest().starts_with("'''") {
+            "'''"
+        } else {
+            "'"
+        };
+        self.pos += quote.len();
+        if quote.len() == 3 {
+            self.eat_new_line();
+        }
+
+        match self.rest().find(quote) {
+            Some(len) => {
+                let value = &self.rest()[..len];
+                self.pos += len + quote.len();
+                Ok(Cow::Borrowed(value))
+            }
+            None => Err(self.error_at(start, "Unterminated string".to_string())),
+        }
+    }
+
+    /// Value of a variable defined in the document or in the environment
+    fn variable_named(&self, name: &str, pos: usize) -> Result<Variable<'de>> {
+        if let Some(value) = self.variables.get(name) {
+            return Ok(value.clone());
+        }
+
+        env::var(name)
+            .map(|value| Variable::String(Cow::Owned(value)))
+            .map_err(|_| {
+                self.error_at(
+                    pos,
+                    format!(
+                        "Variable \"{}\" is not defined in Gura nor as environment variable",
+                        name
+                    ),
+                )
+            })
+    }
+
+    /// Reads a `$name` value
+    fn variable_value(&mut self) -> Result<Variable<'de>> {
+        let start = self.pos;
+        self.pos += 1;
+        let name = self.eat_key();
+        if name.is_empty() {
+            return Err(self.unexpected("variable name"));
+        }
+        self.variable_named(name, start)
+    }
+
+    /// Reads a `$name: value` definition
+    fn variable(&mut self) -> Result<()> {
+        let start = self.pos;
+        self.pos += 1;
+        let name = self.eat_key();
+        if name.is_empty() {
+            return Err(self.unexpected("variable name"));
+        }
+        if !self.eat(":") {
+            return Err(self.unexpected("\":\""));
+        }
+        self.eat_ws();
+
+        let value = match self.peek() {
+            Some(b'"') => Variable::String(self.basic_string()?),
+            Some(b'\'') => Variable::String(self.literal_string()?),
+            Some(b'$') => self.variable_value()?,
+            _ => match self.number()? {
+                Some(Scalar::Integer(value)) => Variable::Integer(value),
+                Some(Scalar::Float(value)) => Variable::Float(value),
+                _ => return Err(self.error("Invalid variable value")),
+            },
+        };
+
+        if self.variables.contains_key(name) {
+            return Err(self.error_at(
+                start,
+                format!("Variable \"{}\" has been already declared", name),
+            ));
+        }
+        self.variables.insert(name, value);
+        Ok(())
+    }
+
+    /// Reads a line of an object up to the value of its pair, if it has one
+    fn object_line(&mut self) -> Result<Line<'de>> {
+        if self.peek() == Some(b'$') {
+            self.variable()?;
+            return Ok(Line::Other);
+        }
+
+        let start = self.pos;
+        let mut level = 0;
+        loop {
+            match self.peek() {
+                Some(b' ') => level += 1,
+                Some(b'\t') => {
+                    return Err(self.error("Tabs are not allowed to define indentation blocks"))
+                }
+                _ => break,
+            }
+            self.pos += 1;
+        }
+
+        let key_pos = self.pos;
+        let key = self.eat_key();
+        self.limits
+            .check_string(key)
+            .map_err(|err| self.locate(err, key_pos))?;
+        if key.is_empty() {
+            self.pos = start;
+            if self.useless_line() {
+                return Ok(Line::Other);
+            }
+            self.pos = key_pos;
+            return Err(self.unexpected("key"));
+        }
+        if !self.eat(":") {
+            return Err(self.unexpected("\":\""));
+        }
+        self.eat_ws();
+
+        if level % 4 != 0 {
+            return Err(self.error_at(
+                start,
+                format!("Indentation block ({}) must be divisible by 4", level),
+            ));
+        }
+        match self.indentation_levels.last() {
+            Some(last) if level > *last => self.indentation_levels.push(level),
+            Some(last) if level < *last => {
+                // Read again by the parent object
+                self.indentation_levels.pop();
+                self.pos = start;
+                return Ok(Line::Break);
+            }
+            Some(_) => {}
+            None if level > 0 => {
+                return Err(self.error_at(
+                    start,
+                    "First pair must have indentation level 0".to_string(),
+                ))
+            }
+            None => self.indentation_levels.push(level),
+        }
+
+        Ok(Line::Pair(key, level, key_pos))
+    }
+
+    /// Ends an object in an array when the next element or the end of the
+    /// array follows
+    fn object_end(&mut self) -> bool {
+        let start = self.pos;
+        self.eat_ws();
+        if let Some(b']') | Some(b',') = self.peek() {
+            self.indentation_levels.pop();
+            true
+        } else {
+            self.pos = start;
+            false
+        }
+    }
+
+    /// Moves to the value of the next pair of the object. Returns `None` once
+    /// the object ends. Repeated keys are read as any other, see
+    /// `ObjectState::is_repeated`
+    pub(crate) fn next_key(&mut self, object: &mut ObjectState<'de>) -> Result<Option<&'de str>> {
+        while !object.done && !self.is_eof() {
+            match self.object_line()? {
+                Line::Pair(key, level, key_pos) => {
+                    let first_pos = *object.keys.entry(key).or_insert(key_pos);
+                    object.repeated = if first_pos == key_pos {
+                        None
+                    } else {
+                        Some((key_pos, first_pos))
+                    };
+                    object.pair = Some((key, level));
+                    object.pairs += 1;
+                    return Ok(Some(key));
+                }
+                Line::Break => break,
+                Line::Other => object.done = self.object_end(),
+            }
+        }
+
+        object.done = true;
+        if !object.ended {
+            object.ended = true;
+            self.depth -= 1;
+        }
+        self.completed = Completed::Object(object.level);
+        Ok(None)
+    }
+
+    /// Error for the repeated key read last, with the positions of both pairs
+    pub(crate) fn repeated_key(&self, object: &ObjectState<'de>) -> Error {
+        let (key, _) = object.pair.expect("a key was read");
+        let (pos, first_pos) = object.repeated.expect("the key is repeated");
+        let (first_line, first_column) = self.position(first_pos);
+        self.error_at(
+            pos,
+            format!(
+                "The key \"{}\" has been already defined at line {} column {}",
+                key, first_line, first_column
+            ),
+        )
+    }
+
+    /// Adds to `found` the last pairs of the object whose pairs are read next
+    /// and of the objects nested in it. The object is read on a copy of the
+    /// parser, so each object is only looked ahead once
+    pub(crate) fn last_pairs(&self, found: &mut LastPairs<'de>) -> Result<()> {
+        self.clone().object_last_pairs(found)
+    }
+
+    fn object_last_pairs(&mut self, found: &mut LastPairs<'de>) -> Result<()> {
+        let start = self.pos;
+        let mut object = ObjectState::default();
+        let mut last = HashMap::new();
+        while let Some(key) = self.next_key(&mut object)? {
+            last.insert(key, object.pair_index());
+            let token = self.parse_value()?;
+            self.value_last_pairs(token, found)?;
+            self.end_value(&mut object)?;
+        }
+        found.insert(start, last);
+        Ok(())
+    }
+
+    fn value_last_pairs(&mut self, token: Token<'de>, found: &mut LastPairs<'de>) -> Result<()> {
+        match token {
+            Token::Scalar(_) => Ok(()),
+            Token::Array => {
+                let mut array = ArrayState::default();
+                while self.next_element(&mut array)? {
+                    let token = self.parse_value()?;
+                    self.value_last_pairs(token, found)?;
+                }
+                Ok(())
+            }
+            Token::Object => self.object_last_pairs(found),
+        }
+    }
+
+    /// Ends the pair whose value was read last
+    pub(crate) fn end_value(&mut self, object: &mut ObjectState<'de>) -> Result<()> {
+        let (key, level) = match object.pair.take() {
+            Some(pair) => pair,
+            None => return Ok(()),
+        };
+
+        match self.completed {
+            Completed::Object(None) => return Err(self.error("Invalid pair")),
+            Completed::Object(Some(child_level)) if child_level == level => {
+                return Err(self.error_at(
+                    self.pos,
+                    format!(
+                        "Wrong indentation level for the pairs of \"{}\" (it has the same indentation level)",
+                        key
+                    ),
+                ));
+            }
+            Completed::Object(Some(child_level))
+                if child_level.max(level) - child_level.min(level) != 4 =>
+            {
+                return Err(self.error("Difference between different indentation levels must be 4"));
+            }
+            Completed::Array => {
+                // Arrays of objects leave the indentation of their elements
+                self.indentation_levels.pop();
+                self.indentation_levels.push(level);
+            }
+            _ => {}
+        }
+
+        self.eat_new_line();
+        object.level = Some(level);
+        object.done = self.object_end();
+        Ok(())
+    }
+
+    /// Moves to the next element of the array. Returns `false` once the array
+    /// ends
+    pub(crate) fn next_element(&mut self, array: &mut ArrayState) -> Result<bool> {
+        if array.done {
+            return Ok(false);
+        }
+
+        if array.started {
+            self.eat_ws();
+            self.eat_new_line();
+            if !self.eat(",") {
+                return self.end_array(array);
+            }
+        }
+        array.started = true;
+
+        while !self.is_eof() && self.useless_line() {}
+        if self.is_eof() {
+            return Err(self.unexpected("\"]\""));
+        }
+
+        let start = self.pos;
+        self.eat_ws();
+        if self.eat("]") {
+            array.done = true;
+            self.depth -= 1;
+            self.completed = Completed::Array;
+            return Ok(false);
+        }
+        self.pos = start;
+        Ok(true)
+    }
+
+    fn end_array(&mut self, array: &mut ArrayState) -> Result<bool> {
+        self.eat_ws();
+        self.eat_new_line();
+        if !self.eat("]") {
+            return Err(self.unexpected("\",\" or \"]\""));
+        }
+        array.done = true;
+        self.depth -= 1;
+        self.completed = Completed::Array;
+        Ok(false)
+    }
+
+    /// Reads the rest of a value without keeping it
+    pub(crate) fn skip(&mut self, token: Token<'de>) -> Result<()> {
+        match token {
+            Token::Scalar(_) => {}
+            Token::Array => {
+                let mut array = ArrayState::default();
+                while self.next_element(&mut array)? {
+                    let token = self.parse_value()?;
+                    self.skip(token)?;
+                }
+            }
+            Token::Object => {
+                let mut object = ObjectState::default();
+                while self.next_key(&mut object)?.is_some() {
+                    let token = self.parse_value()?;
+                    self.skip(token)?;
+                    self.end_value(&mut object)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that only blanks and new lines are left
+    pub(crate) fn end(&mut self) -> Result<()> {
+        self.eat_ws_and_new_lines();
+        if self.is_eof() {
+            Ok(())
+        } else {
+            Err(self.unexpected("end of input"))
+        }
+    }
+}
@@ -1,9 +1,190 @@
-use super::error::{Error, Result};
-use gura::{dump, GuraType};
+use super::error::{Error, ErrorKind, Result};
+use crate::dump::dump;
+use crate::enum_repr::{EnumRepr, VariantNames};
+use gura::GuraType;
 use indexmap::IndexMap;
 use serde::{ser, Serialize};
+use std::convert::TryFrom;
+
+/// Options shared by a `Serializer` and the serializers created for the
+/// nested values.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Options {
+    pub(crate) skip_none_fields: bool,
+    pub(crate) enum_repr: EnumRepr,
+    pub(crate) variant_names: VariantNames,
+    pub(crate) unit_variants_as_index: bool,
+    pub(crate) max_depth: Option<usize>,
+}
+
+impl Options {
+    /// Fails if an array or object at `depth` is nested deeper than allowed
+    pub(crate) fn check_depth(&self, depth: usize) -> Result<()> {
+        match self.max_depth {
+            Some(max) if depth > max => Err(ErrorKind::DepthLimit(max).into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Serializer of Rust values into `gura::GuraType` values.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Serializer {
+    pub(crate) options: Options,
+    /// Arrays and objects that contain the value being serialized
+    depth: usize,
+    /// Newtype structs and `Some` values that contain the value being
+    /// serialized
+    wrappers: usize,
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Serializer::default()
+    }
+
+    /// Omits struct fields whose value is `None` instead of writing them as
+    /// `null`. Disabled by default.
+    ///
+    /// Use `serde_gura::double_option` to still write `null` for some field.
+    pub fn skip_none_fields(mut self, enabled: bool) -> Self {
+        self.options.skip_none_fields = enabled;
+        self
+    }
+
+    /// Sets how enum variants are written. Defaults to `EnumRepr::External`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use serde_derive::Serialize;
+    /// use serde_gura::{EnumRepr, Serializer, VariantNames};
+    ///
+    /// #[derive(Serialize)]
+    /// enum Shape {
+    ///     Circle { radius: f64 },
+    /// }
+    ///
+    /// let serializer = Serializer::new()
+    ///     .enum_repr(EnumRepr::type_value())
+    ///     .variant_names(VariantNames::SnakeCase);
+    /// assert_eq!(
+    ///     serializer.to_string(&Shape::Circle { radius: 1.5 }).unwrap(),
+    ///     "type: \"circle\"\nvalue:\n    radius: 1.5"
+    /// );
+    /// ```
+    pub fn enum_repr(mut self, repr: EnumRepr) -> Self {
+        self.options.enum_repr = repr;
+        self
+    }
+
+    /// Sets how variant names are written, both for enum values and for map
+    /// keys. Defaults to `VariantNames::AsIs`.
+    pub fn variant_names(mut self, names: VariantNames) -> Self {
+        self.options.variant_names = names;
+        self
+    }
+
+    /// Writes unit variants as their index instead of their name (`mode: 2`
+    /// instead of `mode: "Fast"`), for compact machine-generated documents.
+    /// They are read back with `Deserializer::variant_indices`. Map keys are
+    /// still written as names. Disabled by default.
+    pub fn unit_variants_as_index(mut self, enabled: bool) -> Self {
+        self.options.unit_variants_as_index = enabled;
+        self
+    }
 
-pub struct Serializer;
+    /// Fails with `ErrorKind::DepthLimit` if arrays and objects are nested more
+    /// than `depth` levels, instead of overflowing the stack on deeply nested
+    /// or recursive structures. The document itself is the first level and
+    /// enum variants written as objects count as one more. Newtype structs
+    /// and `Some` values nested more than `depth` levels fail too. No limit
+    /// by default.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.options.max_depth = Some(depth);
+        self
+    }
+
+    /// Serializer for the values of a new array or object
+    fn nested(mut self) -> Result<Self> {
+        self.depth += 1;
+        self.options.check_depth(self.depth)?;
+        Ok(self)
+    }
+
+    /// Serializer for the value of a newtype struct or `Some`
+    fn wrapped(mut self) -> Result<Self> {
+        self.wrappers += 1;
+        self.options.check_depth(self.wrappers)?;
+        Ok(self)
+    }
+
+    /// Builds the value of an enum variant following the enum representation.
+    /// `value` is `None` for unit variants.
+    fn variant_value(
+        self,
+        variant: &str,
+        variant_index: u32,
+        value: Option<GuraType>,
+    ) -> Result<GuraType> {
+        let name = self.options.variant_names.apply(variant).into_owned();
+        let id = if value.is_none() && self.options.unit_variants_as_index {
+            GuraType::Integer(variant_index as isize)
+        } else {
+            GuraType::String(name.clone())
+        };
+
+        match (self.options.enum_repr, value) {
+            (EnumRepr::External, None) => Ok(id),
+            (EnumRepr::External, Some(value)) => {
+                let mut hash = IndexMap::new();
+                hash.insert(valid_key(name)?, value);
+                Ok(GuraType::Object(hash))
+            }
+            (EnumRepr::Adjacent { tag, content }, value) => {
+                let mut hash = IndexMap::new();
+                hash.insert(valid_key(tag.to_string())?, id);
+                if let Some(value) = value {
+                    hash.insert(valid_key(content.to_string())?, value);
+                }
+                Ok(GuraType::Object(hash))
+            }
+        }
+    }
+
+    /// Serializes the given data structure as a String of Gura using the
+    /// options of this serializer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use serde_derive::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Config {
+    ///     name: String,
+    ///     comment: Option<String>,
+    /// }
+    ///
+    /// let config = Config {
+    ///     name: "Gura".to_string(),
+    ///     comment: None,
+    /// };
+    /// let serializer = serde_gura::Serializer::new().skip_none_fields(true);
+    /// assert_eq!(serializer.to_string(&config).unwrap(), "name: \"Gura\"");
+    /// ```
+    pub fn to_string<T>(self, value: &T) -> Result<String>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        let result = value.serialize(self)?;
+        match &result {
+            // `empty` is not a valid document, an empty object is an empty document
+            GuraType::Object(obj) if obj.is_empty() => Ok(String::new()),
+            _ => Ok(dump(&result)),
+        }
+    }
+}
 
 impl ser::Serializer for Serializer {
     type Ok = GuraType;
@@ -49,12 +230,30 @@ impl ser::Serializer for Serializer {
         self.serialize_i64(v as i64)
     }
 
+    fn serialize_i128(self, v: i128) -> Result<GuraType> {
+        match isize::try_from(v) {
+            Ok(int) => Ok(GuraType::Integer(int)),
+            Err(_) => Ok(GuraType::BigInteger(v)),
+        }
+    }
+
     fn serialize_u64(self, v: u64) -> Result<GuraType> {
-        Ok(GuraType::Integer(v as isize))
+        self.serialize_i128(v as i128)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<GuraType> {
+        match i128::try_from(v) {
+            Ok(int) => self.serialize_i128(int),
+            Err(_) => Err(ErrorKind::Message(format!(
+                "Integer {} is too big to be represented in Gura",
+                v
+            ))
+            .into()),
+        }
     }
 
     fn serialize_f32(self, v: f32) -> Result<GuraType> {
-        self.serialize_f64(v as f64)
+        self.serialize_f64(widen_f32(v))
     }
 
     fn serialize_f64(self, v: f64) -> Result<GuraType> {
@@ -70,6 +269,7 @@ impl ser::Serializer for Serializer {
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<GuraType> {
+        self.nested()?;
         let vec = value
             .iter()
             .map(|&b| GuraType::Integer(b as isize))
@@ -88,30 +288,35 @@ impl ser::Serializer for Serializer {
     fn serialize_unit_variant(
         self,
         _name: &str,
-        _variant_index: u32,
+        variant_index: u32,
         variant: &str,
     ) -> Result<GuraType> {
-        Ok(GuraType::String(variant.to_owned()))
+        // Adjacently tagged variants are objects
+        if let EnumRepr::Adjacent { .. } = self.options.enum_repr {
+            self.nested()?;
+        }
+        self.variant_value(variant, variant_index, None)
     }
 
     fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<GuraType>
     where
         T: ser::Serialize + ?Sized,
     {
-        value.serialize(self)
+        value.serialize(self.wrapped()?)
     }
 
     fn serialize_newtype_variant<T>(
         self,
         _name: &str,
-        _variant_index: u32,
+        variant_index: u32,
         variant: &str,
         value: &T,
     ) -> Result<GuraType>
     where
         T: ser::Serialize + ?Sized,
     {
-        Ok(singleton_hash(variant.to_string(), to_gura_type(value)?))
+        let value = to_gura_type(value, self.nested()?)?;
+        self.variant_value(variant, variant_index, Some(value))
     }
 
     fn serialize_none(self) -> Result<GuraType> {
@@ -122,7 +327,7 @@ impl ser::Serializer for Serializer {
     where
         V: ser::Serialize + ?Sized,
     {
-        value.serialize(self)
+        value.serialize(self.wrapped()?)
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray> {
@@ -130,7 +335,10 @@ impl ser::Serializer for Serializer {
             None => Vec::new(),
             Some(len) => Vec::with_capacity(len),
         };
-        Ok(SerializeArray { array })
+        Ok(SerializeArray {
+            ser: self.nested()?,
+            array,
+        })
     }
 
     fn serialize_tuple(self, len: usize) -> Result<SerializeArray> {
@@ -144,18 +352,22 @@ impl ser::Serializer for Serializer {
     fn serialize_tuple_variant(
         self,
         _enum: &'static str,
-        _idx: u32,
+        idx: u32,
         variant: &'static str,
         len: usize,
     ) -> Result<SerializeTupleVariant> {
+        // The array is nested in the object of the variant
         Ok(SerializeTupleVariant {
+            ser: self.nested()?.nested()?,
             name: variant,
+            index: idx,
             array: Vec::with_capacity(len),
         })
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap> {
         Ok(SerializeMap {
+            ser: self.nested()?,
             hash: IndexMap::new(),
             next_key: None,
         })
@@ -163,6 +375,7 @@ impl ser::Serializer for Serializer {
 
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<SerializeStruct> {
         Ok(SerializeStruct {
+            ser: self.nested()?,
             hash: IndexMap::new(),
         })
     }
@@ -170,12 +383,15 @@ impl ser::Serializer for Serializer {
     fn serialize_struct_variant(
         self,
         _enum: &'static str,
-        _idx: u32,
+        idx: u32,
         variant: &'static str,
         _len: usize,
     ) -> Result<SerializeStructVariant> {
+        // The fields are nested in the object of the variant
         Ok(SerializeStructVariant {
+            ser: self.nested()?.nested()?,
             name: variant,
+            index: idx,
             hash: IndexMap::new(),
         })
     }
@@ -183,29 +399,36 @@ impl ser::Serializer for Serializer {
 
 #[doc(hidden)]
 pub struct SerializeArray {
+    ser: Serializer,
     array: Vec<GuraType>,
 }
 
 #[doc(hidden)]
 pub struct SerializeTupleVariant {
+    ser: Serializer,
     name: &'static str,
+    index: u32,
     array: Vec<GuraType>,
 }
 
 #[doc(hidden)]
 pub struct SerializeMap {
+    ser: Serializer,
     hash: IndexMap<String, GuraType>, // Must to be a hash
     next_key: Option<String>,
 }
 
 #[doc(hidden)]
 pub struct SerializeStruct {
+    ser: Serializer,
     hash: IndexMap<String, GuraType>, // Must to be a hash
 }
 
 #[doc(hidden)]
 pub struct SerializeStructVariant {
+    ser: Serializer,
     name: &'static str,
+    index: u32,
     hash: IndexMap<String, GuraType>, // Must to be a hash
 }
 
@@ -217,7 +440,7 @@ impl ser::SerializeSeq for SerializeArray {
     where
         T: ser::Serialize + ?Sized,
     {
-        self.array.push(to_gura_type(elem)?);
+        self.array.push(to_gura_type(elem, self.ser)?);
         Ok(())
     }
 
@@ -266,15 +489,13 @@ impl ser::SerializeTupleVariant for SerializeTupleVariant {
     where
         V: ser::Serialize + ?Sized,
     {
-        self.array.push(to_gura_type(v)?);
+        self.array.push(to_gura_type(v, self.ser)?);
         Ok(())
     }
 
     fn end(self) -> Result<GuraType> {
-        Ok(singleton_hash(
-            self.name.to_string(),
-            GuraType::Array(self.array),
-        ))
+        self.ser
+            .variant_value(self.name, self.index, Some(GuraType::Array(self.array)))
     }
 }
 
@@ -286,7 +507,10 @@ impl ser::SerializeMap for SerializeMap {
     where
         T: ser::Serialize + ?Sized,
     {
-        self.next_key = Some(to_gura_type(key)?.to_string());
+        if self.next_key.is_some() {
+            return Err(ErrorKind::ExpectedMapValue.into());
+        }
+        self.next_key = Some(key_to_string(key, self.ser)?);
         Ok(())
     }
 
@@ -294,32 +518,26 @@ impl ser::SerializeMap for SerializeMap {
     where
         T: ser::Serialize + ?Sized,
     {
-        match self.next_key.take() {
-            Some(key) => self.hash.insert(key, to_gura_type(value)?),
-            None => panic!("Serialize_value called before serialize_key"),
-        };
+        let key = self.next_key.take().ok_or(ErrorKind::ExpectedMapKey)?;
+        self.hash.insert(key, to_gura_type(value, self.ser)?);
         Ok(())
     }
 
+    // Goes through `serialize_key` and `serialize_value` so both ways of
+    // serializing an entry generate the same keys
     fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<()>
     where
         K: ser::Serialize + ?Sized,
         V: ser::Serialize + ?Sized,
     {
-        let gura_elem = to_gura_type(key)?;
-
-        // This check prevents https://github.com/gura-conf/serde-gura/issues/3#issue-1005484064
-        let res = if let GuraType::String(str) = gura_elem {
-            str
-        } else {
-            gura_elem.to_string()
-        };
-
-        self.hash.insert(res, to_gura_type(value)?);
-        Ok(())
+        ser::SerializeMap::serialize_key(self, key)?;
+        ser::SerializeMap::serialize_value(self, value)
     }
 
     fn end(self) -> Result<GuraType> {
+        if self.next_key.is_some() {
+            return Err(ErrorKind::ExpectedMapValue.into());
+        }
         Ok(GuraType::Object(self.hash))
     }
 }
@@ -332,7 +550,12 @@ impl ser::SerializeStruct for SerializeStruct {
     where
         V: ser::Serialize + ?Sized,
     {
-        self.hash.insert(key.to_string(), to_gura_type(value)?);
+        if self.ser.options.skip_none_fields && is_none(value) {
+            return Ok(());
+        }
+
+        self.hash
+            .insert(valid_key(key.to_string())?, to_gura_type(value, self.ser)?);
         Ok(())
     }
 
@@ -349,15 +572,371 @@ impl ser::SerializeStructVariant for SerializeStructVariant {
     where
         V: ser::Serialize + ?Sized,
     {
-        self.hash.insert(field.to_string(), to_gura_type(v)?);
+        if self.ser.options.skip_none_fields && is_none(v) {
+            return Ok(());
+        }
+
+        self.hash
+            .insert(valid_key(field.to_string())?, to_gura_type(v, self.ser)?);
         Ok(())
     }
 
     fn end(self) -> Result<GuraType> {
-        Ok(singleton_hash(
-            self.name.to_string(),
-            GuraType::Object(self.hash),
-        ))
+        self.ser
+            .variant_value(self.name, self.index, Some(GuraType::Object(self.hash)))
+    }
+}
+
+/// Serializes map keys. Gura keys are plain strings, so only strings, integers,
+/// chars, bools and unit variants (and newtype structs wrapping them) are accepted.
+struct MapKeySerializer {
+    variant_names: VariantNames,
+}
+
+fn key_must_be_a_string<T>() -> Result<T> {
+    Err(ErrorKind::KeyMustBeAString.into())
+}
+
+impl ser::Serializer for MapKeySerializer {
+    type Ok = String;
+    type Error = Error;
+
+    type SerializeSeq = ser::Impossible<String, Error>;
+    type SerializeTuple = ser::Impossible<String, Error>;
+    type SerializeTupleStruct = ser::Impossible<String, Error>;
+    type SerializeTupleVariant = ser::Impossible<String, Error>;
+    type SerializeMap = ser::Impossible<String, Error>;
+    type SerializeStruct = ser::Impossible<String, Error>;
+    type SerializeStructVariant = ser::Impossible<String, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String> {
+        key_must_be_a_string()
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String> {
+        key_must_be_a_string()
+    }
+
+    fn serialize_char(self, value: char) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_str(self, value: &str) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<String> {
+        key_must_be_a_string()
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        key_must_be_a_string()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        key_must_be_a_string()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &str,
+        _variant_index: u32,
+        variant: &str,
+    ) -> Result<String> {
+        Ok(self.variant_names.apply(variant).into_owned())
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<String>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &str,
+        _variant_index: u32,
+        _variant: &str,
+        _value: &T,
+    ) -> Result<String>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        key_must_be_a_string()
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        key_must_be_a_string()
+    }
+
+    fn serialize_some<V>(self, _value: &V) -> Result<String>
+    where
+        V: ser::Serialize + ?Sized,
+    {
+        key_must_be_a_string()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        key_must_be_a_string()
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        key_must_be_a_string()
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        key_must_be_a_string()
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _enum: &'static str,
+        _idx: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        key_must_be_a_string()
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        key_must_be_a_string()
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        key_must_be_a_string()
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _enum: &'static str,
+        _idx: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        key_must_be_a_string()
+    }
+}
+
+/// Returns true if the value is serialized with `serialize_none`. Used to skip
+/// `None` fields without confusing them with other values written as `null`.
+pub(crate) fn is_none<T>(value: &T) -> bool
+where
+    T: ser::Serialize + ?Sized,
+{
+    matches!(value.serialize(IsNoneSerializer), Ok(true))
+}
+
+// Checks whether a value is `None` without serializing its content. Compound
+// values are never `None`, so they fail early
+struct IsNoneSerializer;
+
+impl ser::Serializer for IsNoneSerializer {
+    type Ok = bool;
+    type Error = Error;
+
+    type SerializeSeq = ser::Impossible<bool, Error>;
+    type SerializeTuple = ser::Impossible<bool, Error>;
+    type SerializeTupleStruct = ser::Impossible<bool, Error>;
+    type SerializeTupleVariant = ser::Impossible<bool, Error>;
+    type SerializeMap = ser::Impossible<bool, Error>;
+    type SerializeStruct = ser::Impossible<bool, Error>;
+    type SerializeStructVariant = ser::Impossible<bool, Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_char(self, _v: char) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_none(self) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<bool>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        Ok(false)
+    }
+
+    fn serialize_unit(self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, _value: &T) -> Result<bool>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        Ok(false)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<bool>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        Ok(false)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(ErrorKind::InvalidType.into())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(ErrorKind::InvalidType.into())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(ErrorKind::InvalidType.into())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(ErrorKind::InvalidType.into())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(ErrorKind::InvalidType.into())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(ErrorKind::InvalidType.into())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(ErrorKind::InvalidType.into())
     }
 }
 
@@ -369,22 +948,48 @@ pub fn to_string<T>(value: &T) -> Result<String>
 where
     T: ser::Serialize,
 {
-    let serializer = Serializer {};
-    let result = value.serialize(serializer)?;
-    Ok(dump(&result))
+    Serializer::new().to_string(value)
 }
 
-fn to_gura_type<T>(elem: T) -> Result<GuraType>
+fn to_gura_type<T>(elem: T, serializer: Serializer) -> Result<GuraType>
 where
     T: ser::Serialize,
 {
-    elem.serialize(Serializer)
+    elem.serialize(serializer)
+}
+
+/// Widening with `as` would write `0.1f32` as `0.10000000149011612`. The
+/// shortest representation of the `f32` is parsed instead, which reads back as
+/// the same `f32`
+pub(crate) fn widen_f32(v: f32) -> f64 {
+    if v.is_finite() {
+        format!("{:?}", v).parse().unwrap_or(v as f64)
+    } else {
+        v as f64
+    }
+}
+
+pub(crate) fn key_to_string<T>(key: T, ser: Serializer) -> Result<String>
+where
+    T: ser::Serialize,
+{
+    valid_key(key.serialize(MapKeySerializer {
+        variant_names: ser.options.variant_names,
+    })?)
+}
+
+/// Gura keys are unquoted, so only non empty keys made of ASCII letters, digits
+/// and underscores can be written (see https://gura.netlify.app/docs/spec#keys)
+pub(crate) fn valid_key(key: String) -> Result<String> {
+    if is_valid_key(&key) {
+        Ok(key)
+    } else {
+        Err(ErrorKind::InvalidKey(key).into())
+    }
 }
 
-fn singleton_hash(k: String, v: GuraType) -> GuraType {
-    let mut hash = IndexMap::new();
-    hash.insert(k, v);
-    GuraType::Object(hash)
+pub(crate) fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
 /// Convert a `T` into `gura::GuraType` which is an enum that can represent
@@ -425,5 +1030,5 @@ pub fn to_value<T>(value: T) -> Result<GuraType>
 where
     T: Serialize,
 {
-    value.serialize(Serializer)
+    value.serialize(Serializer::new())
 }
@@ -0,0 +1,1006 @@
+use crate::de::{Deserializer, Limits, MapKeyDeserializer, Options};
+use crate::enum_repr::{EnumRepr, VariantNames};
+use crate::error::{Error, ErrorKind, Result};
+use crate::parser::{ArrayState, LastPairs, ObjectState, Parser, Scalar, Token};
+use gura::GuraType;
+use indexmap::IndexMap;
+use serde::de::{
+    self, Deserialize, DeserializeOwned, DeserializeSeed, EnumAccess, IgnoredAny, MapAccess,
+    SeqAccess, VariantAccess, Visitor,
+};
+use std::borrow::Cow;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+
+/// What `TextDeserializer` does with a key defined more than once in the same
+/// object. `from_str` always fails on them, as the `gura` parser does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Fails with an error that points to both pairs
+    #[default]
+    Error,
+    /// Keeps the value of the first pair and skips the rest
+    FirstWins,
+    /// Keeps the value of the last pair and skips the rest. The text is read
+    /// twice: the first object that is read is looked ahead, with the objects
+    /// nested in it, to find the last pair of each key.
+    LastWins,
+}
+
+/// Deserializer that reads Gura text while the values are visited, without
+/// building a `gura::GuraType` tree first. Strings without escapes or variables
+/// are borrowed from the text and syntax errors report their line and column.
+///
+/// It reads the same documents as `Deserializer::from_str`, except for the ones
+/// with imports.
+///
+/// # Example
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_derive::Deserialize;
+/// use serde_gura::TextDeserializer;
+///
+/// #[derive(Deserialize)]
+/// struct Service<'a> {
+///     name: &'a str,
+///     timeout: f64,
+/// }
+///
+/// let text = "name: \"api\"\ntimeout: 30";
+/// let mut deserializer = TextDeserializer::new(text).int_to_float(true);
+/// let service = Service::deserialize(&mut deserializer).unwrap();
+/// deserializer.end().unwrap();
+/// assert_eq!(service.name, "api");
+/// assert_eq!(service.timeout, 30.0);
+/// ```
+pub struct TextDeserializer<'de> {
+    parser: Parser<'de>,
+    /// Value started by `deserialize_option` and not read yet
+    peeked: Option<Token<'de>>,
+    started: bool,
+    /// Entries of the document left by the visitor, read by `end`
+    unread: Option<ObjectState<'de>>,
+    /// Objects looked ahead for `DuplicateKeys::LastWins` and not read yet
+    last_pairs: LastPairs<'de>,
+    options: Options,
+}
+
+impl<'de> TextDeserializer<'de> {
+    pub fn new(text: &'de str) -> Self {
+        TextDeserializer {
+            parser: Parser::new(text),
+            peeked: None,
+            started: false,
+            unread: None,
+            last_pairs: LastPairs::new(),
+            options: Options::default(),
+        }
+    }
+
+    /// See `Deserializer::int_to_float`
+    pub fn int_to_float(mut self, enabled: bool) -> Self {
+        self.options.int_to_float = enabled;
+        self
+    }
+
+    /// See `Deserializer::float_to_int`
+    pub fn float_to_int(mut self, enabled: bool) -> Self {
+        self.options.float_to_int = enabled;
+        self
+    }
+
+    /// See `Deserializer::lenient_strings`
+    pub fn lenient_strings(mut self, enabled: bool) -> Self {
+        self.options.lenient_strings = enabled;
+        self
+    }
+
+    /// See `Deserializer::empty_as_unit`
+    pub fn empty_as_unit(mut self, enabled: bool) -> Self {
+        self.options.empty_as_unit = enabled;
+        self
+    }
+
+    /// See `Deserializer::enum_repr`
+    pub fn enum_repr(mut self, repr: EnumRepr) -> Self {
+        self.options.enum_repr = repr;
+        self
+    }
+
+    /// See `Deserializer::variant_names`
+    pub fn variant_names(mut self, names: VariantNames) -> Self {
+        self.options.variant_names = names;
+        self
+    }
+
+    /// See `Deserializer::lenient_variants`
+    pub fn lenient_variants(mut self, enabled: bool) -> Self {
+        self.options.lenient_variants = enabled;
+        self
+    }
+
+    /// See `Deserializer::variant_indices`
+    pub fn variant_indices(mut self, enabled: bool) -> Self {
+        self.options.variant_indices = enabled;
+        self
+    }
+
+    /// Sets what to do with keys defined more than once in the same object.
+    /// Defaults to `DuplicateKeys::Error`.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.options.duplicate_keys = policy;
+        self
+    }
+
+    /// Sets the limits for untrusted input. They are checked while the text
+    /// is read, so the parser stops as soon as one is exceeded.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.parser.set_limits(limits);
+        self
+    }
+
+    /// Iterates over the top-level entries of the document instead of
+    /// deserializing it as a whole. See `Entries`.
+    pub fn into_entries<T>(self) -> Entries<'de, T>
+    where
+        T: DeserializeOwned,
+    {
+        Entries {
+            de: self,
+            state: None,
+            done: false,
+            marker: PhantomData,
+        }
+    }
+
+    /// Iterates over the elements of the array at `path` instead of
+    /// deserializing the document as a whole. See `iter_array`.
+    pub fn into_array_elements<T>(self, path: &str) -> ArrayElements<'de, T>
+    where
+        T: DeserializeOwned,
+    {
+        ArrayElements {
+            de: self,
+            path: path.to_string(),
+            objects: Vec::new(),
+            array: None,
+            done: false,
+            marker: PhantomData,
+        }
+    }
+
+    /// Checks that the rest of the text is valid and empty. Must be called
+    /// after deserializing the document, as the text after the values that
+    /// were visited is not read until then.
+    pub fn end(&mut self) -> Result<()> {
+        if !self.started || self.peeked.is_some() {
+            IgnoredAny::deserialize(&mut *self)?;
+        }
+        if let Some(state) = self.unread.take() {
+            ObjectAccess { de: self, state }.skip_rest()?;
+        }
+        self.parser.end()
+    }
+
+    /// Applies the duplicate key policy to the key read last. Returns whether
+    /// its value must be skipped
+    fn skip_repeated(&self, state: &ObjectState<'de>) -> Result<bool> {
+        if state.is_overridden() {
+            return Ok(true);
+        }
+        if !state.is_repeated() {
+            return Ok(false);
+        }
+        match self.options.duplicate_keys {
+            DuplicateKeys::Error => Err(self.parser.repeated_key(state)),
+            DuplicateKeys::FirstWins => Ok(true),
+            DuplicateKeys::LastWins => Ok(false),
+        }
+    }
+
+    /// State for the object whose pairs are read next. With
+    /// `DuplicateKeys::LastWins`, the object is looked ahead unless it was
+    /// already when a parent object was
+    fn object_state(&mut self) -> Result<ObjectState<'de>> {
+        if self.options.duplicate_keys != DuplicateKeys::LastWins {
+            return Ok(ObjectState::default());
+        }
+        let pos = self.parser.pos();
+        if !self.last_pairs.contains_key(&pos) {
+            self.parser.last_pairs(&mut self.last_pairs)?;
+        }
+        Ok(ObjectState::with_last_pairs(
+            self.last_pairs.remove(&pos).unwrap_or_default(),
+        ))
+    }
+
+    /// Skips the value of the pair read last
+    fn skip_value(&mut self, state: &mut ObjectState<'de>) -> Result<()> {
+        let token = self.parser.parse_value()?;
+        self.parser.skip(token)?;
+        self.parser.end_value(state)
+    }
+
+    fn next_token(&mut self) -> Result<Token<'de>> {
+        if let Some(token) = self.peeked.take() {
+            return Ok(token);
+        }
+        if !self.started {
+            self.started = true;
+            return self.parser.parse_root();
+        }
+        self.parser.parse_value()
+    }
+
+    /// Reads the rest of the value into a tree deserializer, for the types that
+    /// are deserialized the same way from both
+    fn tree(&mut self, token: Token<'de>) -> Result<Deserializer> {
+        let value = self.gura_type(token)?;
+        Ok(Deserializer::with_options(value, self.options.clone()))
+    }
+
+    /// Reads the rest of a value into a `GuraType`, applying the duplicate key
+    /// policy to its objects
+    fn gura_type(&mut self, token: Token<'de>) -> Result<GuraType> {
+        match token {
+            Token::Scalar(scalar) => Ok(scalar.into_gura_type()),
+            Token::Array => {
+                let mut array = ArrayState::default();
+                let mut elements = Vec::new();
+                while self.parser.next_element(&mut array)? {
+                    let token = self.parser.parse_value()?;
+                    elements.push(self.gura_type(token)?);
+                }
+                Ok(GuraType::Array(elements))
+            }
+            Token::Object => {
+                let mut state = self.object_state()?;
+                let mut entries = IndexMap::new();
+                while let Some(key) = self.parser.next_key(&mut state)? {
+                    if self.skip_repeated(&state)? {
+                        self.skip_value(&mut state)?;
+                        continue;
+                    }
+                    let token = self.parser.parse_value()?;
+                    entries.insert(key.to_string(), self.gura_type(token)?);
+                    self.parser.end_value(&mut state)?;
+                }
+                Ok(GuraType::Object(entries))
+            }
+        }
+    }
+
+    /// Reads a value with `read`, skipping it if `read` did not
+    fn value<T, F>(&mut self, read: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        let values = self.parser.values();
+        let value = read(self)?;
+        if self.peeked.is_some() || self.parser.values() == values {
+            IgnoredAny::deserialize(&mut *self)?;
+        }
+        Ok(value)
+    }
+
+    fn visit_array<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut access = ArrayAccess {
+            de: self,
+            state: ArrayState::default(),
+        };
+        let value = visitor.visit_seq(&mut access)?;
+        // Elements left by the visitor are skipped
+        while access.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(value)
+    }
+
+    fn visit_object<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // Only the document has been started
+        let is_root = self.parser.values() == 1;
+        let mut access = ObjectAccess::new(self)?;
+        let value = visitor.visit_map(&mut access)?;
+        if is_root {
+            // Left for `end`, so reading a few entries stops early
+            access.de.unread = Some(access.state);
+        } else {
+            access.skip_rest()?;
+        }
+        Ok(value)
+    }
+}
+
+/// Deserializes an instance of type `T` from Gura text, without building a
+/// `gura::GuraType` tree first. Unlike `from_str`, `T` can borrow strings from
+/// the text and imports are not supported.
+///
+/// # Example
+///
+/// ```
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize, PartialEq, Debug)]
+/// struct User<'a> {
+///     name: &'a str,
+///     roles: Vec<&'a str>,
+/// }
+///
+/// let text = "name: \"Gura\"\nroles: [\"admin\", \"dev\"]";
+/// let user: User = serde_gura::from_str_direct(text).unwrap();
+/// assert_eq!(user, User { name: "Gura", roles: vec!["admin", "dev"] });
+/// ```
+pub fn from_str_direct<'de, T>(s: &'de str) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    let mut deserializer = TextDeserializer::new(s);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Iterator over the top-level entries of a Gura document, created by
+/// `iter_entries` and `TextDeserializer::into_entries`.
+///
+/// Each value is read into a tree and deserialized on its own, so only one
+/// entry is held in memory at a time and an entry that does not match `T`
+/// does not stop the iteration. Syntax errors do, as the rest of the text
+/// cannot be read. The ones found outside of an entry are yielded with an
+/// empty key.
+pub struct Entries<'de, T> {
+    de: TextDeserializer<'de>,
+    /// Document being read, `None` until the first entry
+    state: Option<ObjectState<'de>>,
+    done: bool,
+    marker: PhantomData<T>,
+}
+
+impl<'de, T> Entries<'de, T>
+where
+    T: DeserializeOwned,
+{
+    /// Reads the next key, returning `None` once the document ends
+    fn next_key(&mut self) -> Result<Option<&'de str>> {
+        if self.state.is_none() {
+            match self.de.next_token()? {
+                Token::Object => self.state = Some(self.de.object_state()?),
+                _ => return self.de.end().map(|_| None),
+            }
+        }
+
+        let state = self.state.as_mut().unwrap();
+        while let Some(key) = self.de.parser.next_key(state)? {
+            if !self.de.skip_repeated(state)? {
+                return Ok(Some(key));
+            }
+            self.de.skip_value(state)?;
+        }
+        self.de.end().map(|_| None)
+    }
+
+    /// Reads the value of the current entry. The outer result holds the
+    /// syntax errors, the inner one the errors from `T`
+    fn value(&mut self) -> Result<Result<T>> {
+        let token = self.de.parser.parse_value()?;
+        let mut tree = self.de.tree(token)?;
+        self.de.parser.end_value(self.state.as_mut().unwrap())?;
+        Ok(T::deserialize(&mut tree))
+    }
+}
+
+impl<'de, T> Iterator for Entries<'de, T>
+where
+    T: DeserializeOwned,
+{
+    type Item = (String, Result<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let key = match self.next_key() {
+            Ok(Some(key)) => key,
+            Ok(None) => {
+                self.done = true;
+                return None;
+            }
+            Err(err) => {
+                self.done = true;
+                return Some((String::new(), Err(err)));
+            }
+        };
+
+        let value = self.value().unwrap_or_else(|err| {
+            self.done = true;
+            Err(err)
+        });
+        Some((key.to_string(), value))
+    }
+}
+
+impl<T> FusedIterator for Entries<'_, T> where T: DeserializeOwned {}
+
+/// Iterates over the top-level entries of a Gura document, deserializing each
+/// value as `T`. See `Entries`.
+///
+/// # Example
+///
+/// ```
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Tenant {
+///     plan: String,
+///     seats: u32,
+/// }
+///
+/// let text = "
+/// acme:
+///     plan: \"pro\"
+///     seats: 12
+///
+/// globex:
+///     plan: \"free\"
+///     seats: \"many\"
+/// ";
+/// for (name, tenant) in serde_gura::iter_entries::<Tenant>(text) {
+///     match tenant {
+///         Ok(tenant) => println!("{}: {:?}", name, tenant),
+///         Err(err) => eprintln!("{} was skipped: {}", name, err),
+///     }
+/// }
+/// ```
+pub fn iter_entries<'de, T>(s: &'de str) -> Entries<'de, T>
+where
+    T: DeserializeOwned,
+{
+    TextDeserializer::new(s).into_entries()
+}
+
+/// Iterator over the elements of an array of a Gura document, created by
+/// `iter_array` and `TextDeserializer::into_array_elements`.
+///
+/// Like `Entries`, each element is read into a tree and deserialized on its
+/// own. Elements that do not match `T` are yielded as errors and the iteration
+/// goes on, while syntax errors end it. Once the array ends, the rest of the
+/// document is checked.
+pub struct ArrayElements<'de, T> {
+    de: TextDeserializer<'de>,
+    /// Keys to the array, separated by dots
+    path: String,
+    /// Objects from the document to the array, with the pair being read
+    objects: Vec<ObjectState<'de>>,
+    /// Array being read, `None` until it is found
+    array: Option<ArrayState>,
+    done: bool,
+    marker: PhantomData<T>,
+}
+
+impl<'de, T> ArrayElements<'de, T>
+where
+    T: DeserializeOwned,
+{
+    /// Reads the document until the start of the array at the path
+    fn find_array(&mut self) -> Result<()> {
+        let missing = |path: &str| ErrorKind::MissingPath(path.to_string()).into();
+        if !matches!(self.de.next_token()?, Token::Object) {
+            return Err(missing(&self.path));
+        }
+
+        let path = self.path.clone();
+        let mut keys = path.split('.').peekable();
+        let mut state = self.de.object_state()?;
+        while let Some(key) = keys.next() {
+            loop {
+                let found = match self.de.parser.next_key(&mut state)? {
+                    Some(found) => found,
+                    None => return Err(missing(&path)),
+                };
+                if found == key && !self.de.skip_repeated(&state)? {
+                    break;
+                }
+                self.de.skip_value(&mut state)?;
+            }
+
+            let token = self.de.parser.parse_value()?;
+            self.objects.push(state);
+            match (token, keys.peek().is_none()) {
+                (Token::Array, true) => {
+                    self.array = Some(ArrayState::default());
+                    return Ok(());
+                }
+                (Token::Object, false) => state = self.de.object_state()?,
+                (_, true) => return Err(ErrorKind::ExpectedArray.into()),
+                (_, false) => return Err(ErrorKind::ExpectedMap.into()),
+            }
+        }
+        Err(missing(&path))
+    }
+
+    /// Reads the next element. The outer result holds the syntax errors, the
+    /// inner one the errors from `T`
+    fn next_element(&mut self) -> Result<Option<Result<T>>> {
+        if self.array.is_none() {
+            self.find_array()?;
+        }
+
+        let array = self.array.as_mut().unwrap();
+        if !self.de.parser.next_element(array)? {
+            self.end()?;
+            return Ok(None);
+        }
+        let token = self.de.parser.parse_value()?;
+        let mut tree = self.de.tree(token)?;
+        Ok(Some(T::deserialize(&mut tree)))
+    }
+
+    /// Reads the rest of the document after the array
+    fn end(&mut self) -> Result<()> {
+        while let Some(mut state) = self.objects.pop() {
+            self.de.parser.end_value(&mut state)?;
+            ObjectAccess {
+                de: &mut self.de,
+                state,
+            }
+            .skip_rest()?;
+        }
+        self.de.end()
+    }
+}
+
+impl<'de, T> Iterator for ArrayElements<'de, T>
+where
+    T: DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.next_element() {
+            Ok(Some(element)) => Some(element),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl<T> FusedIterator for ArrayElements<'_, T> where T: DeserializeOwned {}
+
+/// Iterates over the elements of the array at `path`, deserializing each one
+/// as `T`. The path is made of the keys from the document to the array,
+/// separated by dots. See `ArrayElements`.
+///
+/// # Example
+///
+/// ```
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct TangoSinger {
+///     name: String,
+///     year_of_birth: u16,
+/// }
+///
+/// let text = "
+/// music:
+///     tango_singers: [
+///         name: \"Carlos\"
+///         year_of_birth: 1890,
+///
+///         name: \"Aníbal\"
+///         year_of_birth: 1914
+///     ]
+/// ";
+/// let mut born = Vec::new();
+/// for singer in serde_gura::iter_array::<TangoSinger>(text, "music.tango_singers") {
+///     let singer = singer.unwrap();
+///     born.push((singer.name, singer.year_of_birth));
+/// }
+/// assert_eq!(born, [("Carlos".to_string(), 1890), ("Aníbal".to_string(), 1914)]);
+/// ```
+pub fn iter_array<'de, T>(s: &'de str, path: &str) -> ArrayElements<'de, T>
+where
+    T: DeserializeOwned,
+{
+    TextDeserializer::new(s).into_array_elements(path)
+}
+
+fn visit_string<'de, V>(value: Cow<'de, str>, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    match value {
+        Cow::Borrowed(value) => visitor.visit_borrowed_str(value),
+        Cow::Owned(value) => visitor.visit_string(value),
+    }
+}
+
+// Primitives are deserialized the same way as with `Deserializer`, so they
+// accept the same conversions
+macro_rules! deserialize_with_tree {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                let token = self.next_token()?;
+                de::Deserializer::$method(&mut self.tree(token)?, visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut TextDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.next_token()? {
+            Token::Scalar(Scalar::String(value)) => visit_string(value, visitor),
+            Token::Array => self.visit_array(visitor),
+            Token::Object => self.visit_object(visitor),
+            token => self.tree(token)?.deserialize_any(visitor),
+        }
+    }
+
+    deserialize_with_tree! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_unit
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.next_token()? {
+            Token::Scalar(Scalar::String(value)) => visit_string(value, visitor),
+            token => self.tree(token)?.deserialize_str(visitor),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    // Strings are read as their UTF-8 bytes and arrays as a sequence of bytes
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.next_token()? {
+            Token::Scalar(Scalar::String(Cow::Borrowed(value))) => {
+                visitor.visit_borrowed_bytes(value.as_bytes())
+            }
+            Token::Scalar(Scalar::String(Cow::Owned(value))) => {
+                visitor.visit_byte_buf(value.into_bytes())
+            }
+            Token::Array => self.visit_array(visitor),
+            _ => Err(ErrorKind::ExpectedBytes.into()),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.next_token()? {
+            Token::Scalar(Scalar::Null) => visitor.visit_none(),
+            token => {
+                self.peeked = Some(token);
+                visitor.visit_some(self)
+            }
+        }
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.next_token()? {
+            Token::Array => self.visit_array(visitor),
+            token => self.tree(token)?.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.next_token()? {
+            Token::Object => self.visit_object(visitor),
+            token => self.tree(token)?.deserialize_map(visitor),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    // Externally represented variants with a value are read as they come. The
+    // rest of the representations are read into a tree first, as the tag might
+    // come after the value
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match (self.options.enum_repr, self.next_token()?) {
+            (EnumRepr::External, Token::Object) => {
+                let mut access = ObjectAccess::new(self)?;
+                let key = match access.next_pair()? {
+                    Some(key) => key,
+                    None => return Err(ErrorKind::ExpectedEnum.into()),
+                };
+                let options = &access.de.options;
+                let variant =
+                    options
+                        .variant_names
+                        .resolve(key, variants, options.lenient_variants)?;
+                visitor.visit_enum(VariantObject { access, variant })
+            }
+            (_, token) => self.tree(token)?.deserialize_enum(name, variants, visitor),
+        }
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.next_token()? {
+            Token::Scalar(Scalar::String(value)) => visit_string(value, visitor),
+            token => self.tree(token)?.deserialize_identifier(visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let token = self.next_token()?;
+        self.parser.skip(token)?;
+        visitor.visit_unit()
+    }
+}
+
+// Gives access to the elements of an array as they are read
+struct ArrayAccess<'a, 'de> {
+    de: &'a mut TextDeserializer<'de>,
+    state: ArrayState,
+}
+
+impl<'de> SeqAccess<'de> for ArrayAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if !self.de.parser.next_element(&mut self.state)? {
+            return Ok(None);
+        }
+        self.de.value(|de| seed.deserialize(de)).map(Some)
+    }
+}
+
+// Gives access to the entries of an object as they are read
+struct ObjectAccess<'a, 'de> {
+    de: &'a mut TextDeserializer<'de>,
+    state: ObjectState<'de>,
+}
+
+impl<'a, 'de> ObjectAccess<'a, 'de> {
+    fn new(de: &'a mut TextDeserializer<'de>) -> Result<Self> {
+        let state = de.object_state()?;
+        Ok(ObjectAccess { de, state })
+    }
+
+    /// Reads the next key whose value is not skipped by the duplicate key
+    /// policy
+    fn next_pair(&mut self) -> Result<Option<&'de str>> {
+        while let Some(key) = self.de.parser.next_key(&mut self.state)? {
+            if !self.de.skip_repeated(&self.state)? {
+                return Ok(Some(key));
+            }
+            self.de.skip_value(&mut self.state)?;
+        }
+        Ok(None)
+    }
+
+    /// Reads the value of the current pair with `read`
+    fn value<T, F>(&mut self, read: F) -> Result<T>
+    where
+        F: FnOnce(&mut TextDeserializer<'de>) -> Result<T>,
+    {
+        let value = self.de.value(read)?;
+        self.de.parser.end_value(&mut self.state)?;
+        Ok(value)
+    }
+
+    /// Skips the entries left by the visitor
+    fn skip_rest(&mut self) -> Result<()> {
+        if self.state.has_pair() {
+            self.next_value::<IgnoredAny>()?;
+        }
+        while self.next_key::<IgnoredAny>()?.is_some() {
+            self.next_value::<IgnoredAny>()?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> MapAccess<'de> for ObjectAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.state.has_pair() {
+            // The visitor skipped the value of the previous key
+            self.next_value::<IgnoredAny>()?;
+        }
+
+        match self.next_pair()? {
+            Some(key) => seed
+                .deserialize(MapKeyDeserializer {
+                    key: Cow::Borrowed(key),
+                    variant_names: self.de.options.variant_names,
+                    lenient_variants: self.de.options.lenient_variants,
+                })
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        if !self.state.has_pair() {
+            return Err(ErrorKind::ExpectedMap.into());
+        }
+        self.value(|de| seed.deserialize(de))
+    }
+}
+
+/// Enum written as an object with the variant name as its only key
+struct VariantObject<'a, 'de> {
+    access: ObjectAccess<'a, 'de>,
+    variant: &'de str,
+}
+
+impl<'de> VariantObject<'_, 'de> {
+    /// Reads the variant value, failing if the object has more keys
+    fn value<T, F>(mut self, read: F) -> Result<T>
+    where
+        F: FnOnce(&mut TextDeserializer<'de>) -> Result<T>,
+    {
+        let value = self.access.value(read)?;
+        if self.access.next_pair()?.is_some() {
+            return Err(ErrorKind::ExpectedEnum.into());
+        }
+        Ok(value)
+    }
+}
+
+impl<'de> EnumAccess<'de> for VariantObject<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = seed.deserialize(MapKeyDeserializer {
+            key: Cow::Borrowed(self.variant),
+            variant_names: VariantNames::AsIs,
+            lenient_variants: false,
+        })?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantObject<'_, 'de> {
+    type Error = Error;
+
+    // The variant has a value, so it is not a unit variant
+    fn unit_variant(self) -> Result<()> {
+        Err(ErrorKind::ExpectedUnitVariant.into())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.value(|de| seed.deserialize(de))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.value(|de| de::Deserializer::deserialize_seq(de, visitor))
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.value(|de| de::Deserializer::deserialize_map(de, visitor))
+    }
+}
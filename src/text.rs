@@ -0,0 +1,749 @@
+use crate::dump::{write_float, write_string, INDENT};
+use crate::enum_repr::EnumRepr;
+use crate::error::{Error, ErrorKind, Result};
+use crate::ser::{is_none, is_valid_key, key_to_string, widen_f32, Serializer};
+use serde::ser;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt::{Display, Write};
+
+/// Where the value being serialized is written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Position {
+    /// The whole document
+    Root,
+    /// After the `key:` of an object entry
+    Value,
+    /// Element of an array
+    Element,
+}
+
+/// Object being written
+struct Object {
+    position: Position,
+    /// Indentation of the value that contains the object
+    level: usize,
+    /// Indentation of the object entries, known when the first one is written
+    entries_level: usize,
+    len: usize,
+}
+
+/// Array being written. Arrays are written in a single line until one of their
+/// elements is a non empty object, then the elements written so far are moved
+/// to their own lines.
+struct Array {
+    /// Offset right after the opening `[`
+    start: usize,
+    /// Indentation of the value that contains the array
+    level: usize,
+    /// Offsets where the elements written in a single line start
+    elements: Vec<usize>,
+    len: usize,
+    multiline: bool,
+}
+
+/// Serializer that writes Gura text while walking the data, without building a
+/// `gura::GuraType` tree first. The output is the same as `to_string` with the
+/// same options.
+///
+/// # Example
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_derive::Serialize;
+/// use serde_gura::{Serializer, TextSerializer};
+///
+/// #[derive(Serialize)]
+/// struct Config {
+///     name: String,
+///     comment: Option<String>,
+/// }
+///
+/// let config = Config {
+///     name: "Gura".to_string(),
+///     comment: None,
+/// };
+/// let mut serializer = TextSerializer::from(Serializer::new().skip_none_fields(true));
+/// config.serialize(&mut serializer).unwrap();
+/// assert_eq!(serializer.into_string(), "name: \"Gura\"");
+/// ```
+pub struct TextSerializer {
+    output: String,
+    ser: Serializer,
+    /// Indentation of the lines of the value being serialized
+    level: usize,
+    position: Position,
+    /// Arrays being written, the innermost one last
+    arrays: Vec<Array>,
+    /// Arrays and objects that contain the value being serialized
+    depth: usize,
+    /// Newtype structs and `Some` values that contain the value being
+    /// serialized
+    wrappers: usize,
+}
+
+impl Default for TextSerializer {
+    fn default() -> Self {
+        TextSerializer::from(Serializer::new())
+    }
+}
+
+/// Uses the options of the given `Serializer`
+impl From<Serializer> for TextSerializer {
+    fn from(ser: Serializer) -> Self {
+        TextSerializer {
+            output: String::new(),
+            ser,
+            level: 0,
+            position: Position::Root,
+            arrays: Vec::new(),
+            depth: 0,
+            wrappers: 0,
+        }
+    }
+}
+
+impl TextSerializer {
+    pub fn new() -> Self {
+        TextSerializer::default()
+    }
+
+    /// Returns the Gura text written so far
+    pub fn into_string(self) -> String {
+        self.output
+    }
+
+    /// Separates a value from its key
+    fn begin_value(&mut self) {
+        if self.position == Position::Value {
+            self.output.push(' ');
+        }
+    }
+
+    fn write_display<T: Display>(&mut self, value: T) -> Result<()> {
+        self.begin_value();
+        let _ = write!(self.output, "{}", value);
+        Ok(())
+    }
+
+    fn write_str(&mut self, value: &str) -> Result<()> {
+        self.begin_value();
+        write_string(&mut self.output, value);
+        Ok(())
+    }
+
+    /// Checks the depth of a new array or object
+    fn begin_nested(&mut self) -> Result<()> {
+        self.depth += 1;
+        self.ser.options.check_depth(self.depth)
+    }
+
+    /// Writes the value of a newtype struct or `Some`
+    fn write_wrapped<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        self.wrappers += 1;
+        self.ser.options.check_depth(self.wrappers)?;
+        value.serialize(&mut *self)?;
+        self.wrappers -= 1;
+        Ok(())
+    }
+
+    fn begin_object(&mut self) -> Result<Object> {
+        self.begin_nested()?;
+        Ok(Object {
+            position: self.position,
+            level: self.level,
+            entries_level: self.level,
+            len: 0,
+        })
+    }
+
+    /// Writes the key of the next entry of `object`. The value must be
+    /// serialized right after it
+    fn write_key(&mut self, object: &mut Object, key: &str) -> Result<()> {
+        if !is_valid_key(key) {
+            return Err(ErrorKind::InvalidKey(key.to_string()).into());
+        }
+
+        if object.len == 0 {
+            match object.position {
+                Position::Value => {
+                    object.entries_level = object.level + 1;
+                    push_newline(&mut self.output, object.entries_level);
+                }
+                Position::Element => object.entries_level = self.multiline_array(),
+                Position::Root => {}
+            }
+        } else {
+            push_newline(&mut self.output, object.entries_level);
+        }
+
+        self.output.push_str(key);
+        self.output.push(':');
+        object.len += 1;
+        self.position = Position::Value;
+        self.level = object.entries_level;
+        Ok(())
+    }
+
+    fn end_object(&mut self, object: Object) {
+        self.depth -= 1;
+        if object.len == 0 {
+            match object.position {
+                Position::Value => self.output.push_str(" empty"),
+                Position::Element => self.output.push_str("empty"),
+                // An empty object is an empty document
+                Position::Root => {}
+            }
+        }
+    }
+
+    fn begin_array(&mut self) -> Result<()> {
+        self.begin_nested()?;
+        self.begin_value();
+        self.output.push('[');
+        self.arrays.push(Array {
+            start: self.output.len(),
+            level: self.level,
+            elements: Vec::new(),
+            len: 0,
+            multiline: false,
+        });
+        Ok(())
+    }
+
+    /// Writes the separator before the next element of the innermost array
+    fn begin_element(&mut self) {
+        let array = self.arrays.last_mut().expect("elements belong to an array");
+        if array.multiline {
+            if array.len > 0 {
+                self.output.push(',');
+            }
+            push_newline(&mut self.output, array.level + 1);
+            self.level = array.level + 1;
+        } else {
+            if array.len > 0 {
+                self.output.push_str(", ");
+            }
+            array.elements.push(self.output.len());
+            self.level = array.level;
+        }
+        array.len += 1;
+        self.position = Position::Element;
+    }
+
+    /// Called when a non empty object is written as an element of the innermost
+    /// array. Moves its elements to their own lines, if they were not yet, and
+    /// returns the indentation of the elements.
+    fn multiline_array(&mut self) -> usize {
+        let array = self.arrays.last_mut().expect("elements belong to an array");
+        let level = array.level + 1;
+        if array.multiline {
+            return level;
+        }
+
+        array.multiline = true;
+        let tail = self.output.split_off(array.start);
+        let elements = std::mem::take(&mut array.elements);
+
+        // The last element is the object being written, which is still empty
+        for (idx, start) in elements.iter().enumerate().take(elements.len() - 1) {
+            // Strips the `, ` separator
+            let end = elements[idx + 1] - 2;
+            let text = &tail[start - array.start..end - array.start];
+            push_newline(&mut self.output, level);
+            for (line_idx, line) in text.split('\n').enumerate() {
+                if line_idx > 0 {
+                    self.output.push('\n');
+                    self.output.push_str(INDENT);
+                }
+                self.output.push_str(line);
+            }
+            self.output.push(',');
+        }
+        push_newline(&mut self.output, level);
+        self.level = level;
+        level
+    }
+
+    fn end_array(&mut self) {
+        self.depth -= 1;
+        let array = self.arrays.pop().expect("arrays are ended once");
+        if array.multiline {
+            push_newline(&mut self.output, array.level);
+        }
+        self.output.push(']');
+    }
+
+    /// Writes the name or the index of a variant
+    fn write_variant_id(&mut self, variant: &'static str, variant_index: u32) -> Result<()> {
+        if self.ser.options.unit_variants_as_index {
+            self.write_display(variant_index)
+        } else {
+            let name = self.ser.options.variant_names.apply(variant);
+            self.write_str(&name)
+        }
+    }
+
+    /// Starts the object of a newtype, tuple or struct variant and writes the
+    /// key of the variant value
+    fn begin_variant(&mut self, variant: &'static str) -> Result<Object> {
+        let options = self.ser.options;
+        let name = options.variant_names.apply(variant);
+        let mut object = self.begin_object()?;
+        match options.enum_repr {
+            EnumRepr::External => self.write_key(&mut object, &name)?,
+            EnumRepr::Adjacent { tag, content } => {
+                self.write_key(&mut object, tag)?;
+                self.write_str(&name)?;
+                self.write_key(&mut object, content)?;
+            }
+        }
+        Ok(object)
+    }
+}
+
+fn push_newline(output: &mut String, level: usize) {
+    output.push('\n');
+    for _ in 0..level {
+        output.push_str(INDENT);
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut TextSerializer {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = SerializeArray<'a>;
+    type SerializeTuple = SerializeArray<'a>;
+    type SerializeTupleStruct = SerializeArray<'a>;
+    type SerializeTupleVariant = SerializeArray<'a>;
+    type SerializeMap = SerializeMap<'a>;
+    type SerializeStruct = SerializeStruct<'a>;
+    type SerializeStructVariant = SerializeStruct<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.write_display(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.write_display(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.write_display(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.write_display(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.write_display(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.write_display(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.write_display(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.write_display(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.write_display(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.write_display(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        match i128::try_from(v) {
+            Ok(int) => self.write_display(int),
+            Err(_) => Err(ErrorKind::Message(format!(
+                "Integer {} is too big to be represented in Gura",
+                v
+            ))
+            .into()),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.serialize_f64(widen_f32(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.begin_value();
+        write_float(&mut self.output, v);
+        Ok(())
+    }
+
+    fn serialize_char(self, value: char) -> Result<()> {
+        self.write_str(value.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<()> {
+        self.write_str(value)
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        let mut seq = ser::Serializer::serialize_seq(self, Some(value.len()))?;
+        for byte in value {
+            ser::SerializeSeq::serialize_element(&mut seq, byte)?;
+        }
+        ser::SerializeSeq::end(seq)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.begin_value();
+        self.output.push_str("null");
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        match self.ser.options.enum_repr {
+            EnumRepr::External => self.write_variant_id(variant, variant_index),
+            EnumRepr::Adjacent { tag, .. } => {
+                let mut object = self.begin_object()?;
+                self.write_key(&mut object, tag)?;
+                self.write_variant_id(variant, variant_index)?;
+                self.end_object(object);
+                Ok(())
+            }
+        }
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        self.write_wrapped(value)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        let object = self.begin_variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.end_object(object);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        self.write_wrapped(value)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<SerializeArray<'a>> {
+        self.begin_array()?;
+        Ok(SerializeArray {
+            ser: self,
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray<'a>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeArray<'a>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeArray<'a>> {
+        let object = self.begin_variant(variant)?;
+        self.begin_array()?;
+        Ok(SerializeArray {
+            ser: self,
+            variant: Some(object),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap<'a>> {
+        let object = self.begin_object()?;
+        Ok(SerializeMap {
+            ser: self,
+            object,
+            keys: HashSet::new(),
+            pending_value: false,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<SerializeStruct<'a>> {
+        let object = self.begin_object()?;
+        Ok(SerializeStruct {
+            ser: self,
+            object,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeStruct<'a>> {
+        let variant = self.begin_variant(variant)?;
+        let object = self.begin_object()?;
+        Ok(SerializeStruct {
+            ser: self,
+            object,
+            variant: Some(variant),
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct SerializeArray<'a> {
+    ser: &'a mut TextSerializer,
+    /// Object of a tuple variant
+    variant: Option<Object>,
+}
+
+impl<'a> SerializeArray<'a> {
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        self.ser.begin_element();
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        self.ser.end_array();
+        if let Some(variant) = self.variant {
+            self.ser.end_object(variant);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeSeq for SerializeArray<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        SerializeArray::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        SerializeArray::end(self)
+    }
+}
+
+impl<'a> ser::SerializeTuple for SerializeArray<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        SerializeArray::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        SerializeArray::end(self)
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for SerializeArray<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        SerializeArray::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        SerializeArray::end(self)
+    }
+}
+
+impl<'a> ser::SerializeTupleVariant for SerializeArray<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        SerializeArray::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        SerializeArray::end(self)
+    }
+}
+
+#[doc(hidden)]
+pub struct SerializeMap<'a> {
+    ser: &'a mut TextSerializer,
+    object: Object,
+    /// Keys are written as soon as they are serialized, so repeated keys can
+    /// not replace the previous entry
+    keys: HashSet<String>,
+    pending_value: bool,
+}
+
+impl<'a> ser::SerializeMap for SerializeMap<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        if self.pending_value {
+            return Err(ErrorKind::ExpectedMapValue.into());
+        }
+
+        let key = key_to_string(key, self.ser.ser)?;
+        if self.keys.contains(&key) {
+            return Err(ErrorKind::DuplicateKey(key).into());
+        }
+        self.ser.write_key(&mut self.object, &key)?;
+        self.keys.insert(key);
+        self.pending_value = true;
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        if !self.pending_value {
+            return Err(ErrorKind::ExpectedMapKey.into());
+        }
+        self.pending_value = false;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        if self.pending_value {
+            return Err(ErrorKind::ExpectedMapValue.into());
+        }
+        self.ser.end_object(self.object);
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub struct SerializeStruct<'a> {
+    ser: &'a mut TextSerializer,
+    object: Object,
+    /// Object of a struct variant
+    variant: Option<Object>,
+}
+
+impl<'a> SerializeStruct<'a> {
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        if self.ser.ser.options.skip_none_fields && is_none(value) {
+            return Ok(());
+        }
+
+        self.ser.write_key(&mut self.object, key)?;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        self.ser.end_object(self.object);
+        if let Some(variant) = self.variant {
+            self.ser.end_object(variant);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeStruct for SerializeStruct<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        SerializeStruct::end(self)
+    }
+}
+
+impl<'a> ser::SerializeStructVariant for SerializeStruct<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ser::Serialize + ?Sized,
+    {
+        SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        SerializeStruct::end(self)
+    }
+}
+
+/// Serializes the given data structure as a String of Gura writing the text
+/// directly, without building a `gura::GuraType` tree. The output is the same
+/// as `to_string`, but it allocates much less for large documents.
+///
+/// The only difference is for maps that serialize the same key twice: they
+/// fail with `ErrorKind::DuplicateKey` instead of keeping the last value.
+pub fn to_string_direct<T>(value: &T) -> Result<String>
+where
+    T: ser::Serialize + ?Sized,
+{
+    let mut serializer = TextSerializer::new();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_string())
+}
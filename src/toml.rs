@@ -0,0 +1,82 @@
+//! Conversions between TOML and Gura. Requires the `toml` feature.
+
+use crate::de::parse;
+use crate::error::{ErrorKind, Result};
+use crate::transcode::{check_representable, to_document, transcode_from};
+use crate::Deserializer;
+use gura::GuraType;
+use serde::Deserialize;
+use std::convert::TryFrom;
+
+/// Converts a TOML document into a Gura document.
+///
+/// Fails if the document contains datetimes, which have no Gura equivalent.
+///
+/// # Example
+///
+/// ```
+/// let toml = "[database]\nip = \"127.0.0.1\"\nport = [80, 8080]\n";
+/// let gura = serde_gura::toml::to_gura(toml).unwrap();
+/// assert_eq!(gura, "database:\n    ip: \"127.0.0.1\"\n    port: [80, 8080]");
+/// ```
+pub fn to_gura(toml: &str) -> Result<String> {
+    let table: ::toml::Table = toml
+        .parse()
+        .map_err(|e: ::toml::de::Error| ErrorKind::Syntax(e.to_string()))?;
+    if let Some((path, datetime)) = find_datetime(&table, "") {
+        return Err(ErrorKind::Message(format!(
+            "Datetime `{}` at `{}` can not be represented in Gura",
+            datetime, path
+        ))
+        .into());
+    }
+
+    to_document(&transcode_from(::toml::Value::Table(table))?)
+}
+
+fn find_datetime(table: &::toml::Table, prefix: &str) -> Option<(String, ::toml::value::Datetime)> {
+    table.iter().find_map(|(key, value)| {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        find_datetime_in_value(value, path)
+    })
+}
+
+fn find_datetime_in_value(
+    value: &::toml::Value,
+    path: String,
+) -> Option<(String, ::toml::value::Datetime)> {
+    match value {
+        ::toml::Value::Datetime(datetime) => Some((path, *datetime)),
+        ::toml::Value::Table(table) => find_datetime(table, &path),
+        ::toml::Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .find_map(|(idx, elem)| find_datetime_in_value(elem, format!("{}[{}]", path, idx))),
+        _ => None,
+    }
+}
+
+/// Converts a Gura document into a TOML document.
+///
+/// Fails if the document contains `null` values or integers that do not fit in
+/// 64 bits, which have no TOML representation.
+pub fn from_gura(gura: &str) -> Result<String> {
+    let value = parse(gura)?;
+    check_representable(&value, "TOML", |elem| match elem {
+        GuraType::Null => Some("Null value".to_string()),
+        GuraType::BigInteger(int) if i64::try_from(*int).is_err() => {
+            Some(format!("Integer `{}`", int))
+        }
+        _ => None,
+    })?;
+
+    // The TOML serializer visits values more than once, so the document is
+    // loaded into a TOML table instead of being streamed
+    let mut deserializer = Deserializer::from_gura_type(value);
+    let table = ::toml::Table::deserialize(&mut deserializer)?;
+    ::toml::to_string(&table).map_err(|e| ErrorKind::Message(e.to_string()).into())
+}
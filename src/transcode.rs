@@ -0,0 +1,325 @@
+use crate::de::{parse, Deserializer};
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+use crate::dump::dump;
+use crate::error::{Error, ErrorKind, Result};
+use crate::ser::{valid_key, Serializer};
+use gura::GuraType;
+use indexmap::IndexMap;
+use serde::de;
+use serde::ser::{self, Serializer as _};
+use std::cell::Cell;
+use std::fmt;
+
+/// Streams the content of any Serde `Deserializer` into a `gura::GuraType`
+/// without an intermediate representation of the source format.
+///
+/// Map keys must be strings: integer or boolean keys, as in YAML, fail with
+/// `ErrorKind::KeyMustBeAString` instead of being converted into strings, and
+/// strings that can not be written as Gura keys fail with
+/// `ErrorKind::InvalidKey`.
+///
+/// # Example
+///
+/// ```
+/// let json = r#"{"name": "Gura", "versions": [1, 2]}"#;
+/// let mut deserializer = serde_json::Deserializer::from_str(json);
+///
+/// let value = serde_gura::transcode_from(&mut deserializer).unwrap();
+/// assert_eq!(gura::dump(&value), "name: \"Gura\"\nversions: [1, 2]");
+/// ```
+pub fn transcode_from<'de, D>(deserializer: D) -> Result<GuraType>
+where
+    D: de::Deserializer<'de>,
+{
+    let error = Cell::new(None);
+    deserializer
+        .deserialize_any(ValueVisitor { error: &error })
+        .map_err(|err| match error.take() {
+            Some(err) => err,
+            None => ErrorKind::Message(err.to_string()).into(),
+        })
+}
+
+/// Builds a `GuraType` from the values of another format. Scalars are
+/// converted by `Serializer`, so they are written the same way as Rust values
+struct ValueVisitor<'a> {
+    /// Error of ours that made the source deserializer fail, as it can only
+    /// keep its message
+    error: &'a Cell<Option<Error>>,
+}
+
+impl ValueVisitor<'_> {
+    fn fail<E: de::Error>(&self, err: Error) -> E {
+        let source_err = E::custom(&err);
+        self.error.set(Some(err));
+        source_err
+    }
+
+    fn value<E: de::Error>(&self, value: Result<GuraType>) -> std::result::Result<GuraType, E> {
+        value.map_err(|err| self.fail(err))
+    }
+}
+
+impl<'de> de::DeserializeSeed<'de> for ValueVisitor<'_> {
+    type Value = GuraType;
+
+    fn deserialize<D>(self, deserializer: D) -> std::result::Result<GuraType, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> de::Visitor<'de> for ValueVisitor<'_> {
+    type Value = GuraType;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a value that can be represented in Gura")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> std::result::Result<GuraType, E> {
+        self.value(Serializer::new().serialize_bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<GuraType, E> {
+        self.value(Serializer::new().serialize_i64(v))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> std::result::Result<GuraType, E> {
+        self.value(Serializer::new().serialize_i128(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<GuraType, E> {
+        self.value(Serializer::new().serialize_u64(v))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> std::result::Result<GuraType, E> {
+        self.value(Serializer::new().serialize_u128(v))
+    }
+
+    fn visit_f32<E: de::Error>(self, v: f32) -> std::result::Result<GuraType, E> {
+        self.value(Serializer::new().serialize_f32(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> std::result::Result<GuraType, E> {
+        self.value(Serializer::new().serialize_f64(v))
+    }
+
+    fn visit_char<E: de::Error>(self, v: char) -> std::result::Result<GuraType, E> {
+        self.value(Serializer::new().serialize_char(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<GuraType, E> {
+        self.value(Serializer::new().serialize_str(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> std::result::Result<GuraType, E> {
+        self.value(Serializer::new().serialize_bytes(v))
+    }
+
+    fn visit_none<E: de::Error>(self) -> std::result::Result<GuraType, E> {
+        self.value(Serializer::new().serialize_none())
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> std::result::Result<GuraType, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<GuraType, E> {
+        self.value(Serializer::new().serialize_unit())
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> std::result::Result<GuraType, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<GuraType, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut elements = Vec::new();
+        while let Some(elem) = seq.next_element_seed(ValueVisitor { error: self.error })? {
+            elements.push(elem);
+        }
+        Ok(GuraType::Array(elements))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<GuraType, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut obj = IndexMap::new();
+        while let Some(key) = map.next_key_seed(KeyVisitor { error: self.error })? {
+            let value = map.next_value_seed(ValueVisitor { error: self.error })?;
+            obj.insert(key, value);
+        }
+        Ok(GuraType::Object(obj))
+    }
+}
+
+/// Reads a map key, which must be a string that can be written as a Gura key
+struct KeyVisitor<'a> {
+    error: &'a Cell<Option<Error>>,
+}
+
+impl<'de> de::DeserializeSeed<'de> for KeyVisitor<'_> {
+    type Value = String;
+
+    fn deserialize<D>(self, deserializer: D) -> std::result::Result<String, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> de::Visitor<'de> for KeyVisitor<'_> {
+    type Value = String;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string key")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<String, E> {
+        valid_key(v.to_string()).map_err(|err| ValueVisitor { error: self.error }.fail(err))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> std::result::Result<String, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_bool<E: de::Error>(self, _v: bool) -> std::result::Result<String, E> {
+        self.non_string()
+    }
+
+    fn visit_i64<E: de::Error>(self, _v: i64) -> std::result::Result<String, E> {
+        self.non_string()
+    }
+
+    fn visit_i128<E: de::Error>(self, _v: i128) -> std::result::Result<String, E> {
+        self.non_string()
+    }
+
+    fn visit_u64<E: de::Error>(self, _v: u64) -> std::result::Result<String, E> {
+        self.non_string()
+    }
+
+    fn visit_u128<E: de::Error>(self, _v: u128) -> std::result::Result<String, E> {
+        self.non_string()
+    }
+
+    fn visit_f64<E: de::Error>(self, _v: f64) -> std::result::Result<String, E> {
+        self.non_string()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<String, E> {
+        self.non_string()
+    }
+}
+
+impl KeyVisitor<'_> {
+    /// Integer, bool, float and null keys, as in YAML, are not converted into
+    /// strings so the conversion does not change them
+    fn non_string<E: de::Error>(&self) -> std::result::Result<String, E> {
+        Err(ValueVisitor { error: self.error }.fail(ErrorKind::KeyMustBeAString.into()))
+    }
+}
+
+/// Writes a value read from another format as a Gura document. Documents are
+/// objects, so other values fail, and empty objects are written as an empty
+/// document, as `Serializer::to_string` does
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+pub(crate) fn to_document(value: &GuraType) -> Result<String> {
+    let found = match value {
+        GuraType::Object(obj) if obj.is_empty() => return Ok(String::new()),
+        GuraType::Object(_) => return Ok(dump(value)),
+        GuraType::Array(_) => "an array",
+        GuraType::String(_) => "a string",
+        GuraType::Integer(_) | GuraType::BigInteger(_) | GuraType::Float(_) => "a number",
+        GuraType::Bool(_) => "a boolean",
+        GuraType::Null => "null",
+        _ => "a value",
+    };
+    Err(ErrorKind::Message(format!(
+        "Gura documents must be objects, but the root is {}",
+        found
+    ))
+    .into())
+}
+
+/// Streams a Gura document into any Serde `Serializer`.
+pub fn transcode_into<S>(s: &str, serializer: S) -> Result<S::Ok>
+where
+    S: ser::Serializer,
+{
+    let parsed = parse(s)?;
+    transcode_value_into(parsed, serializer)
+}
+
+pub(crate) fn transcode_value_into<S>(value: GuraType, serializer: S) -> Result<S::Ok>
+where
+    S: ser::Serializer,
+{
+    let mut deserializer = Deserializer::from_gura_type(value);
+    serde_transcode::transcode(&mut deserializer, serializer)
+        .map_err(|e| ErrorKind::Message(e.to_string()).into())
+}
+
+/// Walks a Gura value returning an error for the first element that can not be
+/// represented in the `format` target. `unsupported` returns a description of
+/// the offending value.
+#[cfg(any(feature = "json", feature = "toml"))]
+pub(crate) fn check_representable<F>(value: &GuraType, format: &str, unsupported: F) -> Result<()>
+where
+    F: Fn(&GuraType) -> Option<String>,
+{
+    check_path(value, &mut String::new(), format, &unsupported)
+}
+
+#[cfg(any(feature = "json", feature = "toml"))]
+fn check_path<F>(value: &GuraType, path: &mut String, format: &str, unsupported: &F) -> Result<()>
+where
+    F: Fn(&GuraType) -> Option<String>,
+{
+    if let Some(description) = unsupported(value) {
+        let location = if path.is_empty() { "(root)" } else { path };
+        return Err(ErrorKind::Message(format!(
+            "{} at `{}` can not be represented in {}",
+            description, location, format
+        ))
+        .into());
+    }
+
+    let len = path.len();
+    match value {
+        GuraType::Object(obj) => {
+            for (key, elem) in obj {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(key);
+                check_path(elem, path, format, unsupported)?;
+                path.truncate(len);
+            }
+        }
+        GuraType::Array(array) => {
+            for (idx, elem) in array.iter().enumerate() {
+                path.push_str(&format!("[{}]", idx));
+                check_path(elem, path, format, unsupported)?;
+                path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
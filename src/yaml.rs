@@ -0,0 +1,32 @@
+//! Conversions between YAML and Gura. Requires the `yaml` feature.
+
+use crate::de::parse;
+use crate::error::{ErrorKind, Result};
+use crate::transcode::{to_document, transcode_from, transcode_value_into};
+
+/// Converts a single YAML document into a Gura document.
+///
+/// Tagged values and mapping keys that are not strings have no Gura equivalent
+/// and make the conversion fail, as do documents that are not a mapping.
+///
+/// # Example
+///
+/// ```
+/// let yaml = "ip: 127.0.0.1\nport:\n  - 80\n  - 8080\n";
+/// let gura = serde_gura::yaml::to_gura(yaml).unwrap();
+/// assert_eq!(gura, "ip: \"127.0.0.1\"\nport: [80, 8080]");
+/// ```
+pub fn to_gura(yaml: &str) -> Result<String> {
+    let deserializer = serde_yaml::Deserializer::from_str(yaml);
+    to_document(&transcode_from(deserializer)?)
+}
+
+/// Converts a Gura document into a YAML document.
+pub fn from_gura(gura: &str) -> Result<String> {
+    let value = parse(gura)?;
+
+    let mut buffer = Vec::new();
+    let mut serializer = serde_yaml::Serializer::new(&mut buffer);
+    transcode_value_into(value, &mut serializer)?;
+    String::from_utf8(buffer).map_err(|e| ErrorKind::Message(e.to_string()).into())
+}
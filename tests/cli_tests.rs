@@ -0,0 +1,164 @@
+#[cfg(feature = "cli")]
+mod test_cli {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    use std::process::Output;
+
+    /// Runs the `gura` binary with `input` as standard input
+    fn output(args: &[&str], input: &str) -> Output {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_gura"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    /// Runs the `gura` binary and returns its standard output, failing if it
+    /// does not succeed
+    fn run(args: &[&str], input: &str) -> String {
+        let output = output(args, input);
+        assert!(output.status.success(), "gura {:?} failed", args);
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// Runs the `gura` binary and returns its standard error, failing if it
+    /// succeeds
+    fn run_err(args: &[&str], input: &str) -> String {
+        let output = output(args, input);
+        assert_eq!(output.status.code(), Some(1), "gura {:?} succeeded", args);
+        assert!(output.stdout.is_empty());
+        String::from_utf8(output.stderr).unwrap()
+    }
+
+    /// Path to a file with `text` in the temporary directory
+    fn temp_file(name: &str, text: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("gura_cli_{}_{}.ura", name, std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    const DOC: &str = "name: \"api\"\nports: [80, 443]\n";
+
+    #[test]
+    fn test_get_and_set() {
+        assert_eq!(run(&["get", "name"], DOC), "api\n");
+        assert_eq!(run(&["get", "ports"], DOC), "[80, 443]\n");
+        assert_eq!(run(&["get", "ports[1]"], DOC), "443\n");
+        assert_eq!(run(&["get", "ports[1]", "-"], DOC), "443\n");
+
+        assert_eq!(
+            run(&["set", "ports[0]", "8080"], DOC),
+            "name: \"api\"\nports: [8080, 443]\n"
+        );
+        assert_eq!(
+            run(&["set", "name", "[\"a\", \"b\"]"], DOC),
+            "name: [\"a\", \"b\"]\nports: [80, 443]\n"
+        );
+        // Missing objects are created
+        assert_eq!(
+            run(&["set", "db.main.host", "\"localhost\""], DOC),
+            format!("{}db:\n    main:\n        host: \"localhost\"\n", DOC)
+        );
+
+        assert!(run_err(&["get", "missing"], DOC).contains("Key \"missing\" not found"));
+        assert!(run_err(&["get", "ports[2]"], DOC).contains("Index 2 out of bounds"));
+        assert!(run_err(&["set", "ports[2]", "1"], DOC).contains("Index 2 out of bounds"));
+        assert!(run_err(&["set", "name.first", "1"], DOC).contains("non object value"));
+        assert!(run_err(&["set", "name", "1 2"], DOC).starts_with("gura: "));
+    }
+
+    #[test]
+    fn test_invalid_paths() {
+        for path in [
+            "", "a..b", "a.", ".a", "[0]", "a.[0]", "a[0", "a[0]b", "a-b",
+        ] {
+            assert_eq!(
+                run_err(&["get", path], DOC),
+                format!("gura: Invalid path \"{}\"\n", path)
+            );
+            assert!(run_err(&["set", path, "1"], DOC).contains("Invalid path"));
+        }
+        assert_eq!(
+            run_err(&["get", "ports[x]"], DOC),
+            "gura: Invalid array index in path \"ports[x]\"\n"
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(run(&["validate"], DOC), "");
+        assert!(run_err(&["validate"], "a: 1\na: 2").contains("\"a\" has been already defined"));
+        assert!(run_err(&["validate"], "a: @").starts_with("gura: "));
+        assert!(run_err(&["validate", "missing_file.ura"], "").starts_with("gura: "));
+        assert!(run_err(&["unknown"], "").contains("Usage: gura"));
+    }
+
+    #[test]
+    fn test_json() {
+        let json = run(&["to-json"], DOC);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"name": "api", "ports": [80, 443]})
+        );
+
+        assert_eq!(
+            run(&["from-json"], "{\"a\": 1, \"b\": [true, null]}"),
+            "a: 1\nb: [true, null]\n"
+        );
+        assert!(run_err(&["from-json"], "{\"a\": ").starts_with("gura: "));
+        assert!(run_err(&["from-json"], "[1, 2]").contains("Gura documents must be objects"));
+
+        // Documents without keys are empty
+        assert_eq!(run(&["from-json"], "{}"), "");
+        assert_eq!(run(&["fmt"], "# comment\n"), "");
+    }
+
+    #[test]
+    fn test_in_place() {
+        let path = temp_file("in_place", DOC);
+        let file = path.to_str().unwrap();
+        assert_eq!(run(&["set", "-i", "ports[1]", "8443", file], ""), "");
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "name: \"api\"\nports: [80, 8443]\n"
+        );
+        assert_eq!(run(&["set", "name", "\"web\"", file, "--in-place"], ""), "");
+        assert_eq!(run(&["get", "name", file], ""), "web\n");
+        std::fs::remove_file(path).unwrap();
+
+        // Without a file there is nowhere to write
+        assert_eq!(
+            run_err(&["set", "name", "1", "-i"], DOC),
+            "gura: --in-place requires a FILE\n"
+        );
+        assert!(run_err(&["fmt", "-i", "-"], DOC).contains("--in-place requires a FILE"));
+    }
+
+    #[test]
+    fn test_float_round_trip() {
+        let doc = "a: 1.0\nb: 0.30000000000000004\nc: -0.0\nd: [1e-7, 1.5e300, inf, -inf]\n";
+        assert_eq!(run(&["fmt"], doc), doc);
+        assert_eq!(run(&["get", "b"], doc), "0.30000000000000004\n");
+        assert_eq!(run(&["get", "d"], doc), "[1e-7, 1.5e300, inf, -inf]\n");
+
+        let updated = run(&["set", "e", "0.1"], doc);
+        assert_eq!(updated, format!("{}e: 0.1\n", doc));
+
+        let path = temp_file("float", doc);
+        run(&["fmt", "-i", path.to_str().unwrap()], "");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), doc);
+        std::fs::remove_file(path).unwrap();
+    }
+}
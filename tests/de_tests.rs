@@ -1,8 +1,15 @@
 #[cfg(test)]
 mod test_deserialize {
+    use serde::Deserialize as _;
     use serde_derive::{Deserialize, Serialize};
-    use serde_gura::{from_str, Error};
-    use std::{collections::HashMap, vec};
+    use serde_gura::{
+        from_str, from_str_with_limits, Category, Deserializer, EnumRepr, Error, ErrorKind, Limits,
+        VariantNames,
+    };
+    use std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        vec,
+    };
 
     // Some common structs
 
@@ -108,6 +115,49 @@ empty_struct: empty
         assert_eq!(expected, from_str(gura_str).unwrap());
     }
 
+    #[test]
+    fn test_unit() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct UnitStruct;
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct TestStruct {
+            unit: (),
+            unit_struct: UnitStruct,
+            phantom: std::marker::PhantomData<u8>,
+            set: BTreeMap<String, ()>,
+        }
+
+        let value = TestStruct {
+            unit: (),
+            unit_struct: UnitStruct,
+            phantom: std::marker::PhantomData,
+            set: vec![("a".to_string(), ())].into_iter().collect(),
+        };
+        let gura_str = serde_gura::to_string(&value).unwrap();
+        assert_eq!(
+            gura_str,
+            "unit: null\nunit_struct: null\nphantom: null\nset:\n    a: null"
+        );
+        assert_eq!(from_str::<TestStruct>(&gura_str).unwrap(), value);
+
+        // `empty` is only accepted if enabled
+        let gura_str = "unit: empty\nunit_struct: empty\nphantom: null\nset: empty";
+        assert_eq!(
+            from_str::<TestStruct>(gura_str).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedNull)
+        );
+
+        let mut de = Deserializer::from_str(gura_str)
+            .unwrap()
+            .empty_as_unit(true);
+        let expected = TestStruct {
+            set: BTreeMap::new(),
+            ..value
+        };
+        assert_eq!(TestStruct::deserialize(&mut de).unwrap(), expected);
+    }
+
     #[test]
     fn test_invalid_unit() {
         #[derive(Deserialize, PartialEq, Debug)]
@@ -115,9 +165,9 @@ empty_struct: empty
             unit: (),
         }
 
-        let gura_str = r#"unit: null"#;
+        let gura_str = r#"unit: 1"#;
         let your_error = from_str::<'_, TestStruct>(gura_str).unwrap_err();
-        assert_eq!(Error::UnitNotSupported, your_error);
+        assert_eq!(&ErrorKind::ExpectedNull, your_error.kind());
     }
 
     #[test]
@@ -247,12 +297,20 @@ tango_singer:
         let expected = E::Tuple(1, 2);
         assert_eq!(expected, from_str(gura_str).unwrap());
 
+        let gura_str = r#"Struct:
+    a: 1"#;
+        let expected = E::Struct { a: 1 };
+        assert_eq!(expected, from_str(gura_str).unwrap());
+
+        // The variant must be the only key
         let gura_str = r#"Struct:
     a: 1
 # Some other object
 key: "value""#;
-        let expected = E::Struct { a: 1 };
-        assert_eq!(expected, from_str(gura_str).unwrap());
+        assert_eq!(
+            from_str::<E>(gura_str).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedEnum)
+        );
     }
 
     #[test]
@@ -368,8 +426,716 @@ resumable_shards:
         };
 
         // Deserialize it back to a Rust type
-        let deserialized_shards: Shards = serde_gura::from_str(&gura_string).unwrap();
+        let deserialized_shards: Shards = serde_gura::from_str(gura_string).unwrap();
 
         assert_eq!(deserialized_shards, expected);
     }
+
+    #[test]
+    fn test_non_string_object_keys() {
+        #[derive(Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+        enum Level {
+            Info,
+            Warning,
+        }
+
+        #[derive(Debug, Deserialize, Serialize, PartialEq)]
+        struct Keys {
+            levels: BTreeMap<Level, u8>,
+            flags: BTreeMap<bool, String>,
+            ids: BTreeMap<u64, char>,
+        }
+
+        let gura_string = r#"
+levels:
+    Info: 1
+    Warning: 2
+flags:
+    false: "off"
+    true: "on"
+ids:
+    5: "a"
+    10: "b""#;
+
+        let expected = Keys {
+            levels: vec![(Level::Info, 1), (Level::Warning, 2)]
+                .into_iter()
+                .collect(),
+            flags: vec![(false, "off".to_string()), (true, "on".to_string())]
+                .into_iter()
+                .collect(),
+            ids: vec![(5, 'a'), (10, 'b')].into_iter().collect(),
+        };
+
+        let deserialized: Keys = from_str(gura_string).unwrap();
+        assert_eq!(deserialized, expected);
+        assert_eq!(
+            serde_gura::to_string(&expected).unwrap(),
+            gura_string.trim()
+        );
+
+        let invalid = from_str::<BTreeMap<u8, u8>>("not_a_number: 1").unwrap_err();
+        assert_eq!(invalid.kind(), &ErrorKind::ExpectedInteger);
+    }
+
+    #[test]
+    fn test_numeric_coercion() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Settings {
+            timeout: f64,
+            ratio: f32,
+            retries: u32,
+            offset: i8,
+        }
+
+        let gura_str = r#"
+timeout: 30
+ratio: 2
+retries: 3.0
+offset: -1.0"#;
+
+        // Disabled by default, as in the Gura spec
+        assert_eq!(
+            from_str::<Settings>(gura_str).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedFloat)
+        );
+
+        let mut de = Deserializer::from_str(gura_str)
+            .unwrap()
+            .int_to_float(true)
+            .float_to_int(true);
+        let expected = Settings {
+            timeout: 30.0,
+            ratio: 2.0,
+            retries: 3,
+            offset: -1,
+        };
+        assert_eq!(Settings::deserialize(&mut de).unwrap(), expected);
+
+        // Only int to float
+        let mut de = Deserializer::from_str(gura_str).unwrap().int_to_float(true);
+        assert_eq!(
+            Settings::deserialize(&mut de).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedInteger)
+        );
+
+        // Coercions must be lossless
+        let mut de = Deserializer::from_str("value: 16777217")
+            .unwrap()
+            .int_to_float(true);
+        let result = HashMap::<String, f32>::deserialize(&mut de);
+        assert_eq!(
+            result.map_err(Error::into_kind),
+            Err(ErrorKind::InexactFloat("16777217".to_string()))
+        );
+
+        let mut de = Deserializer::from_str("value: 9007199254740993")
+            .unwrap()
+            .int_to_float(true);
+        let result = HashMap::<String, f64>::deserialize(&mut de);
+        assert_eq!(
+            result.map_err(Error::into_kind),
+            Err(ErrorKind::InexactFloat("9007199254740993".to_string()))
+        );
+
+        for value in &["3.5", "-1.0", "inf", "nan"] {
+            let mut de = Deserializer::from_str(&format!("value: {}", value))
+                .unwrap()
+                .float_to_int(true);
+            let result = HashMap::<String, u32>::deserialize(&mut de);
+            assert_eq!(
+                result.map_err(Error::into_kind),
+                Err(ErrorKind::ExpectedInteger)
+            );
+        }
+
+        // Values out of the range of the requested type are never truncated
+        let read_u32 = |value: &str| {
+            let mut de = Deserializer::from_str(&format!("value: {}", value))
+                .unwrap()
+                .float_to_int(true);
+            HashMap::<String, u32>::deserialize(&mut de).map(|map| map["value"])
+        };
+        assert_eq!(read_u32("4294967295.0").unwrap(), u32::MAX);
+        assert_eq!(read_u32("4294967295").unwrap(), u32::MAX);
+        for value in &["5000000000.0", "4294967296", "-1", "-1.0"] {
+            assert_eq!(
+                read_u32(value).map_err(Error::into_kind),
+                Err(ErrorKind::ExpectedInteger),
+                "{}",
+                value
+            );
+        }
+
+        for gura_str in &["value: 300", "value: -1"] {
+            assert_eq!(
+                from_str::<HashMap<String, u8>>(gura_str).map_err(Error::into_kind),
+                Err(ErrorKind::ExpectedInteger)
+            );
+            assert_eq!(
+                serde_gura::from_str_direct::<HashMap<String, u8>>(gura_str)
+                    .map_err(Error::into_kind),
+                Err(ErrorKind::ExpectedInteger)
+            );
+        }
+        assert_eq!(
+            from_str::<HashMap<String, i8>>("value: -129").map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedInteger)
+        );
+        assert_eq!(
+            from_str::<HashMap<String, i8>>("value: -128").unwrap()["value"],
+            i8::MIN
+        );
+        assert_eq!(
+            from_str::<HashMap<String, u128>>("value: 99999999999999999999").unwrap()["value"],
+            99999999999999999999
+        );
+    }
+
+    #[test]
+    fn test_lenient_strings() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Overlay {
+            port: u16,
+            offset: i32,
+            ratio: f32,
+            enabled: bool,
+            name: String,
+        }
+
+        let gura_str = r#"
+port: "8080"
+offset: "-5"
+ratio: "0.1"
+enabled: "true"
+name: "8080""#;
+
+        assert_eq!(
+            from_str::<Overlay>(gura_str).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedInteger)
+        );
+
+        let mut de = Deserializer::from_str(gura_str)
+            .unwrap()
+            .lenient_strings(true);
+        let expected = Overlay {
+            port: 8080,
+            offset: -5,
+            ratio: 0.1,
+            enabled: true,
+            name: "8080".to_string(),
+        };
+        assert_eq!(Overlay::deserialize(&mut de).unwrap(), expected);
+
+        // Strings that do not parse as the requested type are still errors
+        let invalid = vec![
+            (
+                "port: \"80a\"\noffset: 1\nratio: 1.0\nenabled: true\nname: \"\"",
+                ErrorKind::ExpectedInteger,
+            ),
+            (
+                "port: \"70000\"\noffset: 1\nratio: 1.0\nenabled: true\nname: \"\"",
+                ErrorKind::ExpectedInteger,
+            ),
+            (
+                "port: \"-1\"\noffset: 1\nratio: 1.0\nenabled: true\nname: \"\"",
+                ErrorKind::ExpectedInteger,
+            ),
+            (
+                "port: 80\noffset: \"2147483648\"\nratio: 1.0\nenabled: true\nname: \"\"",
+                ErrorKind::ExpectedInteger,
+            ),
+            (
+                "port: 80\noffset: 1\nratio: \"fast\"\nenabled: true\nname: \"\"",
+                ErrorKind::ExpectedFloat,
+            ),
+            (
+                "port: 80\noffset: 1\nratio: 1.0\nenabled: \"yes\"\nname: \"\"",
+                ErrorKind::ExpectedBoolean,
+            ),
+        ];
+        for (gura_str, error) in invalid {
+            let mut de = Deserializer::from_str(gura_str)
+                .unwrap()
+                .lenient_strings(true);
+            assert_eq!(
+                Overlay::deserialize(&mut de).map_err(Error::into_kind),
+                Err(error)
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty_keyword() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug, Default)]
+        struct EmptyStruct {}
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug, Default)]
+        struct Collections {
+            settings: EmptyStruct,
+            map: HashMap<String, u8>,
+            vec: Vec<u8>,
+            set: HashSet<u8>,
+            optional: Option<Vec<u8>>,
+        }
+
+        let gura_str = r#"
+settings: empty
+map: empty
+vec: empty
+set: empty
+optional: empty"#;
+        let expected = Collections {
+            optional: Some(Vec::new()),
+            ..Collections::default()
+        };
+        assert_eq!(from_str::<Collections>(gura_str).unwrap(), expected);
+
+        // Empty objects are written as `empty`
+        let gura_str = serde_gura::to_string(&expected).unwrap();
+        assert_eq!(
+            gura_str,
+            "settings: empty\nmap: empty\nvec: []\nset: []\noptional: []"
+        );
+        assert_eq!(from_str::<Collections>(&gura_str).unwrap(), expected);
+
+        // An empty object is an empty document
+        let gura_str = serde_gura::to_string(&EmptyStruct {}).unwrap();
+        assert_eq!(gura_str, "");
+        assert_eq!(from_str::<EmptyStruct>(&gura_str).unwrap(), EmptyStruct {});
+    }
+
+    #[test]
+    fn test_double_option() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Patch {
+            #[serde(default, with = "serde_gura::double_option")]
+            comment: Option<Option<String>>,
+            plain: Option<String>,
+        }
+
+        assert_eq!(
+            from_str::<Patch>("").unwrap(),
+            Patch {
+                comment: None,
+                plain: None
+            }
+        );
+        assert_eq!(
+            from_str::<Patch>("comment: null\nplain: null").unwrap(),
+            Patch {
+                comment: Some(None),
+                plain: None
+            }
+        );
+        assert_eq!(
+            from_str::<Patch>("comment: \"a\"\nplain: \"b\"").unwrap(),
+            Patch {
+                comment: Some(Some("a".to_string())),
+                plain: Some("b".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_enum_repr_errors() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        enum E {
+            Unit,
+            Newtype(u32),
+            MaxValue,
+        }
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Doc {
+            e: E,
+        }
+
+        fn parse(gura_str: &str, repr: EnumRepr, names: VariantNames) -> Result<E, Error> {
+            let mut deserializer = Deserializer::from_str(gura_str)
+                .unwrap()
+                .enum_repr(repr)
+                .variant_names(names);
+            Doc::deserialize(&mut deserializer).map(|doc| doc.e)
+        }
+
+        let external = EnumRepr::External;
+        let adjacent = EnumRepr::type_value();
+        let as_is = VariantNames::AsIs;
+
+        // Non unit variants need a value
+        assert_eq!(
+            parse(r#"e: "Newtype""#, external, as_is).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedVariantValue)
+        );
+        assert_eq!(
+            parse("e:\n    type: \"Newtype\"", adjacent, as_is).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedVariantValue)
+        );
+
+        // Unit variants can not have one
+        assert_eq!(
+            parse("e:\n    Unit: 1", external, as_is).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedUnitVariant)
+        );
+        assert_eq!(
+            parse("e:\n    type: \"Unit\"\n    value: 1", adjacent, as_is)
+                .map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedUnitVariant)
+        );
+
+        // External representation needs the variant as the only key
+        assert_eq!(
+            parse("e:\n    Newtype: 1\n    Unit: 2", external, as_is).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedEnum)
+        );
+
+        // Adjacent representation needs the tag and no other field
+        assert_eq!(
+            parse(r#"e: "Unit""#, adjacent, as_is).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedEnum)
+        );
+        assert_eq!(
+            parse("e:\n    value: 1", adjacent, as_is).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedEnum)
+        );
+        assert_eq!(
+            parse(
+                "e:\n    type: \"Newtype\"\n    value: 1\n    other: 2",
+                adjacent,
+                as_is
+            )
+            .map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedEnum)
+        );
+        assert_eq!(
+            parse("e:\n    type: \"Newtype\"\n    value: 1", adjacent, as_is).unwrap(),
+            E::Newtype(1)
+        );
+
+        // Names must be written with the configured case
+        assert_eq!(
+            parse(r#"e: "max-value""#, external, VariantNames::KebabCase).unwrap(),
+            E::MaxValue
+        );
+        assert_eq!(
+            parse(r#"e: "MaxValue""#, external, VariantNames::SnakeCase).map_err(Error::into_kind),
+            Err(ErrorKind::Message(
+                "unknown variant `MaxValue`, expected one of `unit`, `newtype`, `max_value`"
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_lenient_variants() {
+        #[derive(Deserialize, PartialEq, Eq, Hash, Debug)]
+        enum Level {
+            Info,
+            MaxValue,
+        }
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        enum Clash {
+            MaxValue,
+            #[serde(rename = "max_value")]
+            Snake,
+        }
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Config {
+            level: Level,
+            limits: HashMap<Level, u32>,
+        }
+
+        fn parse<T: for<'de> serde::Deserialize<'de>>(
+            gura_str: &str,
+            lenient: bool,
+        ) -> Result<T, Error> {
+            let mut deserializer = Deserializer::from_str(gura_str)
+                .unwrap()
+                .lenient_variants(lenient);
+            T::deserialize(&mut deserializer)
+        }
+
+        let mut limits = HashMap::new();
+        limits.insert(Level::Info, 1);
+        limits.insert(Level::MaxValue, 2);
+        let expected = Config {
+            level: Level::Info,
+            limits,
+        };
+
+        for level in &["Info", "info", "INFO"] {
+            for max_value in &["MaxValue", "maxValue", "max_value", "MAX_VALUE"] {
+                let gura_str = format!(
+                    "level: \"{}\"\nlimits:\n    {}: 1\n    {}: 2",
+                    level, level, max_value
+                );
+                assert_eq!(parse::<Config>(&gura_str, true).unwrap(), expected);
+            }
+        }
+        assert_eq!(
+            parse::<HashMap<String, Level>>(r#"level: "max-value""#, true).unwrap()["level"],
+            Level::MaxValue
+        );
+
+        // Disabled by default
+        assert!(parse::<Config>("level: \"info\"\nlimits: empty", false).is_err());
+
+        // Exact matches are never ambiguous
+        assert_eq!(
+            parse::<HashMap<String, Clash>>(r#"v: "max_value""#, true).unwrap()["v"],
+            Clash::Snake
+        );
+        assert_eq!(
+            parse::<HashMap<String, Clash>>(r#"v: "MAX-VALUE""#, true).map_err(Error::into_kind),
+            Err(ErrorKind::AmbiguousVariant("MAX-VALUE".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_from_value() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        enum Kind {
+            Web { ports: Vec<u16> },
+            Db,
+        }
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Host {
+            name: String,
+            kind: Kind,
+            tags: HashSet<String>,
+        }
+
+        let value = gura::parse(
+            r#"
+hosts: [
+    name: "a"
+    kind:
+        Web:
+            ports: [80, 443]
+    tags: ["x", "y"],
+    name: "b"
+    kind: "Db"
+    tags: empty
+]"#,
+        )
+        .unwrap();
+
+        // The parsed value is moved into the deserializer, not written back to text
+        let hosts: HashMap<String, Vec<Host>> = serde_gura::from_value(value).unwrap();
+        assert_eq!(
+            hosts["hosts"],
+            vec![
+                Host {
+                    name: "a".to_string(),
+                    kind: Kind::Web {
+                        ports: vec![80, 443]
+                    },
+                    tags: vec!["x".to_string(), "y".to_string()].into_iter().collect(),
+                },
+                Host {
+                    name: "b".to_string(),
+                    kind: Kind::Db,
+                    tags: HashSet::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Config {
+            name: String,
+            tags: Vec<String>,
+            server: HashMap<String, u16>,
+        }
+
+        // 2 levels and 8 values, with "admin_port" as the longest string
+        let text = r#"
+name: "api"
+tags: ["a", "bb"]
+server:
+    port: 8080
+    admin_port: 8081
+"#;
+        fn read(text: &str, limits: Limits) -> Result<Config, Error> {
+            from_str_with_limits(text, &limits)
+        }
+
+        let limits = Limits::new()
+            .max_depth(2)
+            .max_nodes(8)
+            .max_string_length(10)
+            .max_input_size(text.len());
+        assert!(read(text, limits).is_ok());
+
+        assert_eq!(
+            read(text, Limits::new().max_depth(1)).map_err(Error::into_kind),
+            Err(ErrorKind::DepthLimit(1))
+        );
+        assert_eq!(
+            read(text, Limits::new().max_nodes(7)).map_err(Error::into_kind),
+            Err(ErrorKind::NodeLimit(7))
+        );
+        assert_eq!(
+            read(text, Limits::new().max_string_length(9)).map_err(Error::into_kind),
+            Err(ErrorKind::StringLengthLimit(9))
+        );
+        assert_eq!(
+            read(text, Limits::new().max_input_size(10)).map_err(Error::into_kind),
+            Err(ErrorKind::InputSizeLimit(10))
+        );
+
+        // The limits are checked before any value is converted, so they are
+        // reported instead of the errors from the values
+        let text = "name: 1\ntags: [[[[\"deep\"]]]]\nserver: empty";
+        assert_eq!(
+            read(text, Limits::new().max_depth(3)).map_err(Error::into_kind),
+            Err(ErrorKind::DepthLimit(3))
+        );
+        assert!(matches!(
+            read(text, Limits::new()).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedString)
+        ));
+
+        // The size is checked before parsing, so unterminated arrays that the
+        // parser would not finish are rejected
+        assert_eq!(
+            Deserializer::from_str_with_limits("a: [", &Limits::new().max_input_size(3))
+                .map_err(Error::into_kind)
+                .unwrap_err(),
+            ErrorKind::InputSizeLimit(3)
+        );
+
+        // Depth and values are checked before `gura` parses the text, so deep
+        // input fails instead of overflowing its stack
+        let deep = format!("a: {}", "[".repeat(100_000));
+        assert_eq!(
+            from_str_with_limits::<HashMap<String, Vec<u8>>>(&deep, &Limits::new().max_depth(64))
+                .map_err(Error::into_kind),
+            Err(ErrorKind::DepthLimit(64))
+        );
+        let long = format!("a: [{}]", "1, ".repeat(100_000));
+        assert_eq!(
+            from_str_with_limits::<HashMap<String, Vec<u8>>>(&long, &Limits::new().max_nodes(1000))
+                .map_err(Error::into_kind),
+            Err(ErrorKind::NodeLimit(1000))
+        );
+        // Imports are left to `gura`
+        assert!(matches!(
+            from_str_with_limits::<HashMap<String, u8>>(
+                "import \"missing_file.ura\"\na: 1",
+                &Limits::new().max_depth(2)
+            )
+            .map_err(Error::into_kind),
+            Err(ErrorKind::Io(_))
+        ));
+
+        // Limits apply to any type and to deserializers created from a `GuraType`
+        assert_eq!(
+            Deserializer::from_str_with_limits("", &Limits::new().max_depth(0))
+                .map_err(Error::into_kind)
+                .unwrap_err(),
+            ErrorKind::DepthLimit(0)
+        );
+        let value = gura::parse("a: \"long string\"").unwrap();
+        let limits = Limits::new().max_string_length(4).max_input_size(1);
+        assert_eq!(
+            Deserializer::from_gura_type_with_limits(value, &limits)
+                .map_err(Error::into_kind)
+                .unwrap_err(),
+            ErrorKind::StringLengthLimit(4)
+        );
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(untagged)]
+        enum Nested {
+            Leaf(u8),
+            Array(Vec<Nested>),
+        }
+
+        fn nested(depth: usize) -> Nested {
+            (0..depth).fold(Nested::Leaf(1), |value, _| Nested::Array(vec![value]))
+        }
+
+        // The document and 64 arrays
+        let text = format!("a: {}1{}", "[".repeat(64), "]".repeat(64));
+        let expected = nested(64);
+        let value: HashMap<String, Nested> = from_str(&text).unwrap();
+        assert_eq!(value["a"], expected);
+        let value: HashMap<String, Nested> = serde_gura::from_str_direct(&text).unwrap();
+        assert_eq!(value["a"], expected);
+
+        let value: HashMap<String, Nested> =
+            from_str_with_limits(&text, &Limits::new().max_depth(65)).unwrap();
+        assert_eq!(value["a"], expected);
+        assert_eq!(
+            from_str_with_limits::<HashMap<String, Nested>>(&text, &Limits::new().max_depth(64))
+                .map_err(Error::into_kind),
+            Err(ErrorKind::DepthLimit(64))
+        );
+
+        // Nested objects
+        let mut text = String::new();
+        for level in 0..32 {
+            text.push_str(&"    ".repeat(level));
+            text.push_str("a:\n");
+        }
+        text.push_str(&"    ".repeat(32));
+        text.push_str("b: 1");
+        assert!(Deserializer::from_str_with_limits(&text, &Limits::new().max_depth(33)).is_ok());
+        assert_eq!(
+            Deserializer::from_str_with_limits(&text, &Limits::new().max_depth(32))
+                .map_err(Error::into_kind)
+                .unwrap_err(),
+            ErrorKind::DepthLimit(32)
+        );
+    }
+
+    #[test]
+    fn test_error_categories() {
+        let category = |text: &str| {
+            from_str_with_limits::<HashMap<String, u8>>(text, &Limits::new().max_nodes(3))
+                .unwrap_err()
+                .classify()
+        };
+
+        assert_eq!(category("import \"missing_file.ura\"\na: 1"), Category::Io);
+        assert_eq!(category("a: 1\na: 2"), Category::Syntax);
+        assert_eq!(category("a: true"), Category::Data);
+        assert_eq!(category("a: 1\nb: 2\nc: 3"), Category::Limit);
+
+        let error = from_str::<u8>("a: 1\na: 2").unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::Syntax(_)));
+        assert!(matches!(error.into_kind(), ErrorKind::Syntax(_)));
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let position = |text: &str| {
+            let error = from_str::<HashMap<String, u8>>(text).unwrap_err();
+            (error.line(), error.column())
+        };
+
+        assert_eq!(position("a: 1\na: 2"), (Some(2), Some(1)));
+        assert_eq!(position("a: 1\nb: [1, 2 3]"), (Some(2), Some(10)));
+        // Columns count characters, not bytes
+        assert_eq!(position("a: \"ñ\"\nb: @"), (Some(2), Some(4)));
+        // Errors from the data or from imports have no position
+        assert_eq!(position("a: true"), (None, None));
+        assert_eq!(position("import \"missing_file.ura\"\na: 1"), (None, None));
+
+        let error = from_str::<u8>("a: 1\na: 2").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Input text does not have a valid Gura format. Parsing failed with error \"The key \"a\" has been already defined\" at line 2 column 1"
+        );
+    }
 }
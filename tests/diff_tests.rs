@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod test_diff {
+    use gura::GuraType;
+    use serde_gura::{diff, diff_with_options, Change, DiffOptions};
+
+    #[test]
+    fn test_ignores_formatting_and_comments() {
+        let old = r#"
+# Old comment
+database:
+    ip: "127.0.0.1"
+    port: [80,   8080]
+"#;
+        let new = r#"
+database:
+    # New comment
+    ip: "127.0.0.1"
+    port: [80, 8080]
+"#;
+        assert!(diff(old, new).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_objects() {
+        let old = r#"
+name: "Gura"
+version: 1
+removed: true
+"#;
+        let new = r#"
+name: "Gura"
+version: 2
+added:
+    nested: 1.5
+"#;
+        let result = diff(old, new).unwrap();
+        let expected = vec![
+            Change::Changed {
+                path: "version".to_string(),
+                old: GuraType::Integer(1),
+                new: GuraType::Integer(2),
+            },
+            Change::Removed {
+                path: "removed".to_string(),
+                value: GuraType::Bool(true),
+            },
+            Change::Added {
+                path: "added".to_string(),
+                value: gura::parse("nested: 1.5").unwrap(),
+            },
+        ];
+        assert_eq!(result.changes(), expected.as_slice());
+
+        let expected_str = r#"~ version: 1 -> 2
+- removed: true
++ added:
++     nested: 1.5
+"#;
+        assert_eq!(result.to_string(), expected_str);
+    }
+
+    #[test]
+    fn test_arrays_positional() {
+        let old = "values: [1, 2, 3]";
+        let new = "values: [1, 5]";
+        let result = diff(old, new).unwrap();
+        assert_eq!(result.to_string(), "~ values[1]: 2 -> 5\n- values[2]: 3\n");
+    }
+
+    #[test]
+    fn test_arrays_by_key() {
+        let old = r#"
+tango_singers: [
+    name: "Carlos"
+    year_of_birth: 1890,
+
+    name: "Aníbal"
+    year_of_birth: 1914
+]"#;
+        let new = r#"
+tango_singers: [
+    name: "Aníbal"
+    year_of_birth: 1915,
+
+    name: "Roberto"
+    year_of_birth: 1907
+]"#;
+        let options = DiffOptions::new().array_key("name");
+        let result = diff_with_options(old, new, &options).unwrap();
+        let paths: Vec<&str> = result.changes().iter().map(Change::path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                r#"tango_singers[name = "Carlos"]"#,
+                r#"tango_singers[name = "Aníbal"].year_of_birth"#,
+                r#"tango_singers[name = "Roberto"]"#,
+            ]
+        );
+
+        // Without the key every element is compared by position
+        let result = diff(old, new).unwrap();
+        assert_eq!(result.changes().len(), 4);
+    }
+}
@@ -1,9 +1,14 @@
 #[cfg(test)]
 mod test_serialize {
-    use std::collections::HashMap;
+    use std::cell::RefCell;
+    use std::collections::{BTreeMap, HashMap};
+    use std::rc::Rc;
 
-    use serde_derive::Serialize;
-    use serde_gura::to_string;
+    use serde::ser::{Serialize, SerializeMap, Serializer};
+    use serde::Deserialize as _;
+    use serde_derive::{Deserialize, Serialize};
+    use serde_gura::{from_str, from_str_with_limits, to_string, Error, ErrorKind, Limits};
+    use serde_gura::{Deserializer, EnumRepr, Serializer as GuraSerializer, VariantNames};
 
     #[test]
     fn test_struct() {
@@ -126,4 +131,596 @@ k:
 
         assert_eq!(sss_str, expected.trim());
     }
+
+    #[test]
+    fn test_invalid_keys() {
+        for key in &["my key", "a.b", "", "ñandú"] {
+            let mut map = HashMap::new();
+            map.insert(key.to_string(), 1);
+            assert_eq!(
+                to_string(&map).map_err(Error::into_kind),
+                Err(ErrorKind::InvalidKey(key.to_string()))
+            );
+        }
+
+        #[derive(Serialize)]
+        struct Renamed {
+            #[serde(rename = "max-connections")]
+            max_connections: u32,
+        }
+
+        let renamed = Renamed {
+            max_connections: 10,
+        };
+        assert_eq!(
+            to_string(&renamed).map_err(Error::into_kind),
+            Err(ErrorKind::InvalidKey("max-connections".to_string()))
+        );
+
+        let mut map = HashMap::new();
+        map.insert("valid_Key_0", 1);
+        assert_eq!(to_string(&map).unwrap(), "valid_Key_0: 1");
+    }
+
+    #[test]
+    fn test_map_split_entries() {
+        // Serializes entries calling `serialize_key` and `serialize_value` separately
+        struct Split(Vec<(&'static str, u32)>);
+
+        impl Serialize for Split {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(Some(self.0.len()))?;
+                for (key, value) in &self.0 {
+                    map.serialize_key(key)?;
+                    map.serialize_value(value)?;
+                }
+                map.end()
+            }
+        }
+
+        let split = Split(vec![("a", 1), ("b", 2)]);
+        let mut combined = BTreeMap::new();
+        combined.insert("a", 1);
+        combined.insert("b", 2);
+        assert_eq!(to_string(&split).unwrap(), "a: 1\nb: 2");
+        assert_eq!(to_string(&split).unwrap(), to_string(&combined).unwrap());
+    }
+
+    #[test]
+    fn test_map_protocol_errors() {
+        struct ValueWithoutKey;
+
+        impl Serialize for ValueWithoutKey {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_value(&1)?;
+                map.end()
+            }
+        }
+
+        struct KeyWithoutValue;
+
+        impl Serialize for KeyWithoutValue {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_key("a")?;
+                map.end()
+            }
+        }
+
+        assert_eq!(
+            to_string(&ValueWithoutKey).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedMapKey)
+        );
+        assert_eq!(
+            to_string(&KeyWithoutValue).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedMapValue)
+        );
+    }
+
+    #[test]
+    fn test_non_string_keys() {
+        #[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+        enum Level {
+            Info,
+            Tuple(u8, u8),
+        }
+
+        #[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+        struct Id(u32);
+
+        let map: BTreeMap<u32, bool> = vec![(1, true), (20, false)].into_iter().collect();
+        assert_eq!(to_string(&map).unwrap(), "1: true\n20: false");
+
+        let map: BTreeMap<bool, char> = vec![(false, 'a')].into_iter().collect();
+        assert_eq!(to_string(&map).unwrap(), "false: \"a\"");
+
+        let map: BTreeMap<Level, u8> = vec![(Level::Info, 1)].into_iter().collect();
+        assert_eq!(to_string(&map).unwrap(), "Info: 1");
+
+        let map: BTreeMap<Id, u8> = vec![(Id(7), 1)].into_iter().collect();
+        assert_eq!(to_string(&map).unwrap(), "7: 1");
+
+        // Compound keys can not be represented
+        let map: BTreeMap<Level, u8> = vec![(Level::Tuple(1, 2), 1)].into_iter().collect();
+        assert_eq!(
+            to_string(&map).map_err(Error::into_kind),
+            Err(ErrorKind::KeyMustBeAString)
+        );
+
+        let map: BTreeMap<(u8, u8), u8> = vec![((1, 2), 1)].into_iter().collect();
+        assert_eq!(
+            to_string(&map).map_err(Error::into_kind),
+            Err(ErrorKind::KeyMustBeAString)
+        );
+
+        let map: BTreeMap<Option<u8>, u8> = vec![(None, 1)].into_iter().collect();
+        assert_eq!(
+            to_string(&map).map_err(Error::into_kind),
+            Err(ErrorKind::KeyMustBeAString)
+        );
+
+        // Negative integers are not valid keys
+        let map: BTreeMap<i32, u8> = vec![(-1, 1)].into_iter().collect();
+        assert_eq!(
+            to_string(&map).map_err(Error::into_kind),
+            Err(ErrorKind::InvalidKey("-1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_float_fidelity() {
+        #[derive(Serialize, Deserialize, Debug)]
+        struct Floats {
+            single: f32,
+            double: f64,
+        }
+
+        let cases: Vec<(f32, f64, &str)> = vec![
+            (0.1, 0.1, "single: 0.1\ndouble: 0.1"),
+            (1.0, 100.0, "single: 1.0\ndouble: 100.0"),
+            (-0.0, -0.0, "single: -0.0\ndouble: -0.0"),
+            (f32::NAN, f64::INFINITY, "single: nan\ndouble: inf"),
+            (f32::NEG_INFINITY, 1e20, "single: -inf\ndouble: 1e20"),
+            (
+                f32::MAX,
+                f64::MAX,
+                "single: 3.4028235e38\ndouble: 1.7976931348623157e308",
+            ),
+            (
+                f32::MIN_POSITIVE,
+                f64::MIN_POSITIVE,
+                "single: 1.1754944e-38\ndouble: 2.2250738585072014e-308",
+            ),
+        ];
+
+        for (single, double, expected) in cases {
+            let floats = Floats { single, double };
+            let gura_str = to_string(&floats).unwrap();
+            assert_eq!(gura_str, expected);
+
+            // Floats must be read back with the same bits
+            let deserialized: Floats = from_str(&gura_str).unwrap();
+            assert_eq!(deserialized.single.to_bits(), single.to_bits());
+            assert_eq!(deserialized.double.to_bits(), double.to_bits());
+        }
+    }
+
+    #[test]
+    fn test_skip_none_fields() {
+        #[derive(Serialize)]
+        struct Inner {
+            value: Option<u32>,
+        }
+
+        #[derive(Serialize)]
+        enum Variant {
+            Struct { a: Option<u32>, b: u32 },
+        }
+
+        #[derive(Serialize)]
+        struct Config {
+            name: String,
+            comment: Option<String>,
+            unit: (),
+            inner: Inner,
+            list: Vec<Option<u32>>,
+            variant: Variant,
+            #[serde(with = "serde_gura::double_option")]
+            explicit_null: Option<Option<u32>>,
+            #[serde(with = "serde_gura::double_option")]
+            unset: Option<Option<u32>>,
+        }
+
+        let config = Config {
+            name: "Gura".to_string(),
+            comment: None,
+            unit: (),
+            inner: Inner { value: None },
+            list: vec![None, Some(1)],
+            variant: Variant::Struct { a: None, b: 2 },
+            explicit_null: Some(None),
+            unset: None,
+        };
+
+        // Only struct fields are skipped, `null` elements in arrays are kept
+        let expected = r#"name: "Gura"
+unit: null
+inner: empty
+list: [null, 1]
+variant:
+    Struct:
+        b: 2
+explicit_null: null"#;
+        let serializer = GuraSerializer::new().skip_none_fields(true);
+        assert_eq!(serializer.to_string(&config).unwrap(), expected);
+
+        // Disabled by default
+        let expected = r#"name: "Gura"
+comment: null
+unit: null
+inner:
+    value: null
+list: [null, 1]
+variant:
+    Struct:
+        a: null
+        b: 2
+explicit_null: null
+unset: null"#;
+        assert_eq!(to_string(&config).unwrap(), expected);
+        assert_eq!(GuraSerializer::new().to_string(&config).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_enum_repr() {
+        #[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug)]
+        enum Level {
+            Info,
+            MaxValue,
+        }
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        enum Shape {
+            Point,
+            Circle(f64),
+            Line(u32, u32),
+            Rect { width: u32 },
+        }
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Config {
+            level: Level,
+            shapes: Vec<Shape>,
+            limits: BTreeMap<Level, u32>,
+        }
+
+        let config = Config {
+            level: Level::MaxValue,
+            shapes: vec![
+                Shape::Point,
+                Shape::Circle(1.5),
+                Shape::Line(1, 2),
+                Shape::Rect { width: 3 },
+            ],
+            limits: vec![(Level::Info, 1), (Level::MaxValue, 2)]
+                .into_iter()
+                .collect(),
+        };
+
+        let cases = vec![
+            (
+                EnumRepr::External,
+                VariantNames::AsIs,
+                r#"level: "MaxValue"
+shapes: [
+    "Point",
+    Circle: 1.5,
+    Line: [1, 2],
+    Rect:
+        width: 3
+]
+limits:
+    Info: 1
+    MaxValue: 2"#,
+            ),
+            (
+                EnumRepr::External,
+                VariantNames::SnakeCase,
+                r#"level: "max_value"
+shapes: [
+    "point",
+    circle: 1.5,
+    line: [1, 2],
+    rect:
+        width: 3
+]
+limits:
+    info: 1
+    max_value: 2"#,
+            ),
+            (
+                EnumRepr::type_value(),
+                VariantNames::Lowercase,
+                r#"level:
+    type: "maxvalue"
+shapes: [
+    type: "point",
+    type: "circle"
+    value: 1.5,
+    type: "line"
+    value: [1, 2],
+    type: "rect"
+    value:
+        width: 3
+]
+limits:
+    info: 1
+    maxvalue: 2"#,
+            ),
+            (
+                EnumRepr::Adjacent {
+                    tag: "kind",
+                    content: "data",
+                },
+                VariantNames::AsIs,
+                r#"level:
+    kind: "MaxValue"
+shapes: [
+    kind: "Point",
+    kind: "Circle"
+    data: 1.5,
+    kind: "Line"
+    data: [1, 2],
+    kind: "Rect"
+    data:
+        width: 3
+]
+limits:
+    Info: 1
+    MaxValue: 2"#,
+            ),
+        ];
+
+        for (repr, names, expected) in cases {
+            let serializer = GuraSerializer::new().enum_repr(repr).variant_names(names);
+            let gura_str = serializer.to_string(&config).unwrap();
+            assert_eq!(gura_str, expected);
+
+            let mut deserializer = Deserializer::from_str(&gura_str)
+                .unwrap()
+                .enum_repr(repr)
+                .variant_names(names);
+            assert_eq!(Config::deserialize(&mut deserializer).unwrap(), config);
+        }
+
+        // Kebab case names can only be used as values
+        let serializer = GuraSerializer::new().variant_names(VariantNames::KebabCase);
+        assert_eq!(
+            serializer.to_string(&Level::MaxValue).unwrap(),
+            "\"max-value\""
+        );
+
+        #[derive(Serialize)]
+        enum Limit {
+            MaxValue(u32),
+        }
+
+        assert_eq!(
+            serializer
+                .to_string(&Limit::MaxValue(1))
+                .map_err(Error::into_kind),
+            Err(ErrorKind::InvalidKey("max-value".to_string()))
+        );
+        let serializer = serializer.enum_repr(EnumRepr::type_value());
+        assert_eq!(
+            serializer.to_string(&Limit::MaxValue(1)).unwrap(),
+            "type: \"max-value\"\nvalue: 1"
+        );
+    }
+
+    #[test]
+    fn test_unit_variants_as_index() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        enum Mode {
+            Slow,
+            Normal,
+            Fast,
+            Custom(u32),
+        }
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Config {
+            mode: Mode,
+            modes: Vec<Mode>,
+        }
+
+        let config = Config {
+            mode: Mode::Fast,
+            modes: vec![Mode::Slow, Mode::Custom(5)],
+        };
+
+        let serializer = GuraSerializer::new().unit_variants_as_index(true);
+        let gura_str = serializer.to_string(&config).unwrap();
+        assert_eq!(gura_str, "mode: 2\nmodes: [\n    0,\n    Custom: 5\n]");
+
+        let mut deserializer = Deserializer::from_str(&gura_str)
+            .unwrap()
+            .variant_indices(true);
+        assert_eq!(Config::deserialize(&mut deserializer).unwrap(), config);
+
+        // Indices are not accepted by default
+        assert!(from_str::<Config>(&gura_str).is_err());
+
+        // Adjacent tags are written as indices too
+        let serializer = serializer.enum_repr(EnumRepr::type_value());
+        let gura_str = serializer.to_string(&Mode::Normal).unwrap();
+        assert_eq!(gura_str, "type: 1");
+        let mut deserializer = Deserializer::from_str(&gura_str)
+            .unwrap()
+            .enum_repr(EnumRepr::type_value())
+            .variant_indices(true);
+        assert_eq!(Mode::deserialize(&mut deserializer).unwrap(), Mode::Normal);
+
+        // Out of range indices
+        let mut deserializer = Deserializer::from_str("mode: 4\nmodes: []")
+            .unwrap()
+            .variant_indices(true);
+        assert_eq!(
+            Config::deserialize(&mut deserializer).map_err(Error::into_kind),
+            Err(ErrorKind::Message(
+                "invalid value: integer `4`, expected variant index 0 <= i < 4".to_string()
+            ))
+        );
+
+        // Non unit variants still need their value
+        let mut deserializer = Deserializer::from_str("mode: 3\nmodes: []")
+            .unwrap()
+            .variant_indices(true);
+        assert_eq!(
+            Config::deserialize(&mut deserializer).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedVariantValue)
+        );
+    }
+
+    /// List nested one object per node
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Node {
+        value: u32,
+        next: Option<Box<Node>>,
+    }
+
+    fn list(len: u32) -> Node {
+        (1..len).fold(
+            Node {
+                value: 0,
+                next: None,
+            },
+            |next, value| Node {
+                value,
+                next: Some(Box::new(next)),
+            },
+        )
+    }
+
+    /// Newtype struct that contains itself `len` times
+    #[derive(Serialize)]
+    struct Recursive(Option<Box<Recursive>>);
+
+    impl Recursive {
+        fn new(len: usize) -> Self {
+            (0..len).fold(Recursive(None), |next, _| Recursive(Some(Box::new(next))))
+        }
+    }
+
+    /// Drops the nested values one by one instead of recursively
+    impl Drop for Recursive {
+        fn drop(&mut self) {
+            let mut next = self.0.take();
+            while let Some(mut node) = next {
+                next = node.0.take();
+            }
+        }
+    }
+
+    /// Node of a graph that can have cycles, written as the array of its children
+    struct GraphNode {
+        children: RefCell<Vec<Rc<GraphNode>>>,
+    }
+
+    impl Serialize for GraphNode {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.children.borrow().iter().map(|child| &**child))
+        }
+    }
+
+    #[test]
+    fn test_max_depth() {
+        // 10 nested objects
+        let value = list(10);
+        assert!(GuraSerializer::new()
+            .max_depth(10)
+            .to_string(&value)
+            .is_ok());
+        assert_eq!(
+            GuraSerializer::new()
+                .max_depth(9)
+                .to_string(&value)
+                .map_err(Error::into_kind),
+            Err(ErrorKind::DepthLimit(9))
+        );
+
+        // The levels are counted as the deserializer does
+        let text = to_string(&value).unwrap();
+        let limits = Limits::new().max_depth(10);
+        assert_eq!(from_str_with_limits::<Node>(&text, &limits).unwrap(), value);
+        let limits = Limits::new().max_depth(9);
+        assert_eq!(
+            from_str_with_limits::<Node>(&text, &limits).map_err(Error::into_kind),
+            Err(ErrorKind::DepthLimit(9))
+        );
+
+        // Variants written as objects are one more level
+        #[derive(Serialize)]
+        enum Shape {
+            Point,
+            Circle(f64),
+            Line(u32, u32),
+            Rect { width: u32 },
+        }
+
+        let shapes = [
+            (Shape::Point, 0, 1),
+            (Shape::Circle(1.5), 1, 1),
+            (Shape::Line(1, 2), 2, 2),
+            (Shape::Rect { width: 3 }, 2, 2),
+        ];
+        for (shape, external, adjacent) in shapes {
+            for (repr, depth) in [
+                (EnumRepr::External, external),
+                (EnumRepr::type_value(), adjacent),
+            ] {
+                let serializer = GuraSerializer::new().enum_repr(repr);
+                assert!(serializer.max_depth(depth).to_string(&shape).is_ok());
+                if depth > 0 {
+                    assert_eq!(
+                        serializer
+                            .max_depth(depth - 1)
+                            .to_string(&shape)
+                            .map_err(Error::into_kind),
+                        Err(ErrorKind::DepthLimit(depth - 1))
+                    );
+                }
+            }
+        }
+
+        // Cycles fail instead of overflowing the stack
+        let root = Rc::new(GraphNode {
+            children: RefCell::new(Vec::new()),
+        });
+        root.children.borrow_mut().push(Rc::clone(&root));
+        assert_eq!(
+            GuraSerializer::new()
+                .max_depth(100)
+                .to_string(&*root)
+                .map_err(Error::into_kind),
+            Err(ErrorKind::DepthLimit(100))
+        );
+        root.children.borrow_mut().clear();
+
+        // So do deeply nested newtype structs and options
+        let deep = Recursive::new(100_000);
+        assert_eq!(
+            GuraSerializer::new()
+                .max_depth(100)
+                .to_string(&deep)
+                .map_err(Error::into_kind),
+            Err(ErrorKind::DepthLimit(100))
+        );
+        // Each node is a newtype struct and an option
+        assert!(GuraSerializer::new()
+            .max_depth(100)
+            .to_string(&Recursive::new(49))
+            .is_ok());
+    }
 }
@@ -0,0 +1,918 @@
+#[cfg(test)]
+mod test_text_deserializer {
+    use serde::de::{
+        self, Deserialize, Deserializer as _, IgnoredAny, MapAccess, SeqAccess, Visitor,
+    };
+    use serde_derive::Deserialize;
+    use serde_gura::{
+        from_str, from_str_direct, iter_array, iter_entries, Deserializer, DuplicateKeys, EnumRepr,
+        Error, ErrorKind, Limits, TextDeserializer, VariantNames,
+    };
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+    use std::fmt;
+
+    /// Any Gura value, keeping the order of the keys
+    #[derive(Debug)]
+    enum Value {
+        Null,
+        Bool(bool),
+        Integer(i128),
+        Float(f64),
+        String(String),
+        Array(Vec<Value>),
+        Object(Vec<(String, Value)>),
+    }
+
+    impl PartialEq for Value {
+        fn eq(&self, other: &Value) -> bool {
+            match (self, other) {
+                (Value::Null, Value::Null) => true,
+                (Value::Bool(a), Value::Bool(b)) => a == b,
+                (Value::Integer(a), Value::Integer(b)) => a == b,
+                (Value::Float(a), Value::Float(b)) => {
+                    a.to_bits() == b.to_bits() || a.is_nan() && b.is_nan()
+                }
+                (Value::String(a), Value::String(b)) => a == b,
+                (Value::Array(a), Value::Array(b)) => a == b,
+                (Value::Object(a), Value::Object(b)) => a == b,
+                _ => false,
+            }
+        }
+    }
+
+    struct ValueVisitor;
+
+    impl<'de> Visitor<'de> for ValueVisitor {
+        type Value = Value;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("any Gura value")
+        }
+
+        fn visit_unit<E>(self) -> Result<Value, E> {
+            Ok(Value::Null)
+        }
+
+        fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+            Ok(Value::Bool(v))
+        }
+
+        fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+            Ok(Value::Integer(v as i128))
+        }
+
+        fn visit_i128<E>(self, v: i128) -> Result<Value, E> {
+            Ok(Value::Integer(v))
+        }
+
+        fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+            Ok(Value::Float(v))
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+            Ok(Value::String(v.to_string()))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+            let mut elements = Vec::new();
+            while let Some(element) = seq.next_element()? {
+                elements.push(element);
+            }
+            Ok(Value::Array(elements))
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+            let mut entries = Vec::new();
+            while let Some(entry) = map.next_entry()? {
+                entries.push(entry);
+            }
+            Ok(Value::Object(entries))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Value {
+        fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+            deserializer.deserialize_any(ValueVisitor)
+        }
+    }
+
+    /// Both deserializers must read the same value, or fail both
+    fn assert_same(text: &str) -> Option<Value> {
+        let expected = from_str::<Value>(text);
+        let direct = from_str_direct::<Value>(text);
+        match (expected, direct) {
+            (Ok(expected), Ok(direct)) => {
+                assert_eq!(expected, direct, "{:?}", text);
+                Some(direct)
+            }
+            (Err(_), Err(_)) => None,
+            (expected, direct) => panic!(
+                "{:?}\nfrom_str: {:?}\nfrom_str_direct: {:?}",
+                text, expected, direct
+            ),
+        }
+    }
+
+    const VALID: &[&str] = &[
+        "",
+        "\n\n  \n",
+        "# Only a comment",
+        "title: \"Gura Example\"",
+        "a: 1\nb: -2\nc: +3\nd: 1_000\ne: 0xDEAD_BEEF\nf: 0o755\ng: 0b1101",
+        "big: 123456789012345678901234567890\nneg: -170141183460469231731687303715884105728",
+        "a: 1.5\nb: -0.01\nc: 5e+22\nd: 1E6\ne: -2E-2\nf: 6.626e-34\ng: 224_617.445_991",
+        "a: inf\nb: +inf\nc: -inf\nd: nan\ne: +nan\nf: -nan",
+        "a: true\nb: false\nc: null\nd: empty",
+        "a: \"tab\\t quote\\\" backslash\\\\ dollar\\$ \\u00e9 \\U0001F600 \\b\\f\\n\\r\\q\"",
+        "a: 'C:\\Users\\nodejs\\templates'\nb: '<\\i\\c*\\s*>'",
+        "a: \"\"\"\nRoses are red\nViolets are blue\"\"\"",
+        "a: \"\"\"Roses are red\n  Violets are blue\"\"\"",
+        "a: \"\"\"\nThe quick brown \\\n\n\n    fox jumps over \\\n    the lazy dog.\"\"\"",
+        "a: '''\nFirst line\n  Second line'''",
+        "a: '''I [dw]on't need \\d{2} apples'''",
+        "a: \"\"",
+        "a: ''",
+        "a: \"Multi\nline in single quotes\"",
+        "$name: \"Gura\"\n$port: 8080\n$pi: 3.14\na: $name\nb: $port\nc: $pi\nd: \"Hi $name at $port, $pi\"",
+        "$a: \"x\"\n$b: $a\nc: $b",
+        "$a: 'lit'\nc: \"$a$a\"",
+        "a: 1\n$x: 2\nb: $x",
+        "a: [1, 2, 3]\nb: []\nc: [ ]\nd: [\"a\", 'b', 1.5, true, null, empty]",
+        "a: [[1, 2], [3, [4, 5]], []]",
+        "a: [1, 2, ]",
+        "a: [\n    1,\n    2\n]",
+        "a: [\n    1,\n\n    # Comment\n    2\n]",
+        "a: [\n    1,\n    2,\n]",
+        "a: [1\n]",
+        "a: [1\n\n]",
+        "a: [ \t1 ,\t2 ]",
+        "an_object:\n    username: \"Stephen\"\n    pass: \"Hawking\"",
+        "a:\n    b:\n        c:\n            d: 1\n        e: 2\n    f: 3\ng: 4",
+        "a:\n    b: 1\n\n    # Comment\n\n    c: 2\nd: 3",
+        "a: # Comment\n    b: 1",
+        "a:   \n    b: 1",
+        "a: 1 # Comment\nb: 2   \n# Comment",
+        "a: 1\r\nb:\r\n    c: 2\r\n",
+        "a: 1\n\n\n",
+        "a: 1\n    ",
+        "# Comment\n\n$x: 1 # Comment\n\t# Tabs before the first pair\na: $x",
+        "tango_singers: [\n    user1:\n        name: \"Carlos\"\n        surname: \"Gardel\"\n        year_of_birth: 1890,\n    user2:\n        name: \"Aníbal\"\n        surname: \"Troilo\"\n        year_of_birth: 1914\n]",
+        "a: [\n    b: 1\n    c: [1, 2]\n    d:\n        e: 3,\n    f: 4\n]",
+        "a: [\n    b:\n        c: 1\n]\nd: 2",
+        "a: [\n    [\n        b: 1\n    ],\n    2\n]",
+        "a: [b: 1]",
+        "a: [\n    b: 1\n    ,\n    c: 2\n]",
+        "a:\n    b: [\n        c: 1,\n        d: 2\n    ]\n    e: 3",
+        "services:\n    nginx:\n        host: \"127.0.0.1\"\n        port: 80\n    apache:\n        virtual_host: \"10.10.10.4\"\n        port: 81",
+        "a: 1\n    b: 2",
+        "a:\n    b: 1\n$x: 2\n    c: $x",
+        "true_key: true\nnull_key: null\nempty_key: empty\ninf_key: inf\n_: 1\n0: 2",
+        "a: \"ñandú 😀\"\nb: 'ñ'",
+        "a: \"\\\n\"",
+    ];
+
+    const INVALID: &[&str] = &[
+        "a",
+        "a:",
+        "a: \n",
+        "a:\nb: 1",
+        "a: 1\na: 2",
+        "a:\n    b: 1\n    b: 2",
+        "a: 1 2",
+        "a: nullx",
+        "a: truex",
+        "a: 1.2.3",
+        "a: @",
+        "a: \"unterminated",
+        "a: 'unterminated",
+        "a: \"\"\"unterminated\"\"",
+        "a: [1, 2",
+        "a: [1 2]",
+        "a: [,1]",
+        "a: [1\n\n\n]",
+        "a: [1 # Comment\n]",
+        "a: ]",
+        "  a: 1",
+        "a:\n  b: 1",
+        "a:\n    b:\n            c: 1",
+        "a:\n\tb: 1",
+        "a: 1\n\t\nb: 2",
+        "a: 1\n]",
+        "a: 1\n,",
+        "a: $undefined_variable_for_tests",
+        "a: \"$undefined_variable_for_tests\"",
+        "a: \"$\"",
+        "$a: 'lit'\nc: \"${a}\"",
+        "$x: 1\n$x: 2",
+        "$x: true",
+        "$x: 123456789012345678901234567890",
+        "$x 1",
+        "a: [\n    b: 1\nc: 2\n]",
+        "a:\n    b: 1\n        c: 2\nd: 3",
+        "a:\n    b: [c: 1]",
+        "a: [\n$x: 1\n]",
+        "a: b: 1",
+        "a: \"\\u00\"",
+        "a-b: 1",
+        "a : 1",
+        "import \"file.ura\"\na: 1",
+    ];
+
+    #[test]
+    fn test_same_values() {
+        for text in VALID {
+            assert!(assert_same(text).is_some(), "{:?} is valid", text);
+        }
+        for text in INVALID {
+            assert!(assert_same(text).is_none(), "{:?} is not valid", text);
+        }
+    }
+
+    #[test]
+    fn test_environment_variables() {
+        std::env::set_var("SERDE_GURA_STREAM_TEST", "from env");
+        let value = assert_same("a: $SERDE_GURA_STREAM_TEST\nb: \"$SERDE_GURA_STREAM_TEST!\"");
+        assert_eq!(
+            value,
+            Some(Value::Object(vec![
+                ("a".to_string(), Value::String("from env".to_string())),
+                ("b".to_string(), Value::String("from env!".to_string())),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_borrowed_strings() {
+        #[derive(Deserialize, Debug)]
+        struct Strings<'a> {
+            plain: &'a str,
+            literal: &'a str,
+            multiline: &'a str,
+            #[serde(borrow)]
+            escaped: Cow<'a, str>,
+            #[serde(borrow)]
+            variable: Cow<'a, str>,
+            #[serde(borrow)]
+            keys: HashMap<&'a str, &'a str>,
+        }
+
+        let text = r#"
+$name: "Gura"
+plain: "plain"
+literal: 'C:\path'
+multiline: """
+two
+lines"""
+escaped: "new\nline"
+variable: "Hi $name"
+keys:
+    key: "value"
+"#;
+        let strings: Strings = from_str_direct(text).unwrap();
+        assert_eq!(strings.plain, "plain");
+        assert_eq!(strings.literal, "C:\\path");
+        assert_eq!(strings.multiline, "two\nlines");
+        assert_eq!(strings.escaped, Cow::<str>::Owned("new\nline".to_string()));
+        assert!(matches!(strings.escaped, Cow::Owned(_)));
+        assert_eq!(strings.variable, "Hi Gura");
+        assert_eq!(strings.keys["key"], "value");
+
+        // Escaped strings can not be borrowed
+        assert!(from_str_direct::<Strings>(&text.replace("\"plain\"", "\"pl\\tain\"")).is_err());
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let error = |text: &str| {
+            let err = from_str_direct::<Value>(text).unwrap_err();
+            let position = (err.line().unwrap(), err.column().unwrap());
+            match err.into_kind() {
+                ErrorKind::Syntax(msg) => (msg, position),
+                other => panic!("{:?}", other),
+            }
+        };
+        let expected = |msg: &str, line, column| (msg.to_string(), (line, column));
+
+        assert_eq!(
+            error("a: 1\nb:\n    c: [1, 2\n"),
+            expected("Expected \",\" or \"]\" but got end of input", 4, 1)
+        );
+        assert_eq!(
+            error("a: 1\nb:\n   c: 2"),
+            expected("Indentation block (3) must be divisible by 4", 3, 1)
+        );
+        assert_eq!(
+            error("a: 1\nb: 2\na: 3"),
+            expected(
+                "The key \"a\" has been already defined at line 1 column 1",
+                3,
+                1
+            )
+        );
+        assert_eq!(
+            error("a:\n    ñ: \"ñ\" @"),
+            expected("Expected key but got \"ñ\"", 2, 5)
+        );
+        assert_eq!(
+            error("a: \"$nope_not_defined_for_tests\""),
+            expected(
+                "Variable \"nope_not_defined_for_tests\" is not defined in Gura nor as environment variable",
+                1,
+                5
+            )
+        );
+        // gura panics on escapes that are not valid code points
+        assert_eq!(error("a: \"\\uD800\""), expected("Bad hex value", 1, 6));
+        assert_eq!(
+            error("import \"other.ura\""),
+            expected("Imports are only supported by `from_str`", 1, 1)
+        );
+
+        // The position is part of the message of the error
+        let err = from_str_direct::<Value>("a: 1\na: 2").unwrap_err();
+        assert!(err.to_string().ends_with("\" at line 2 column 1"));
+
+        // Limits exceeded while reading point to the value
+        let err = TextDeserializer::new("a: 1\nb: [[1]]")
+            .limits(Limits::new().max_depth(2))
+            .deserialize_any(IgnoredAny)
+            .unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::DepthLimit(2));
+        assert_eq!((err.line(), err.column()), (Some(2), Some(5)));
+    }
+
+    #[test]
+    fn test_structs_and_enums() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        enum Shape {
+            Point,
+            Circle(f64),
+            Line(u32, u32),
+            Rect { width: u32, height: u32 },
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Drawing {
+            name: String,
+            shapes: Vec<Shape>,
+            origin: Option<(i32, i32)>,
+            scale: Option<f64>,
+            layers: HashMap<String, Vec<Shape>>,
+        }
+
+        let text = r#"
+name: "Drawing"
+shapes: [
+    "Point",
+    Circle: 1.5,
+    Line: [1, 2],
+    Rect:
+        width: 3
+        height: 4
+]
+origin: null
+scale: 2.0
+layers:
+    background: ["Point"]
+    foreground: []
+"#;
+        let expected: Drawing = from_str(text).unwrap();
+        assert_eq!(from_str_direct::<Drawing>(text).unwrap(), expected);
+
+        // Variant names and representation options are the same
+        let text = r#"
+shapes: [
+    type: "point",
+    type: "circle"
+    value: 1.5,
+    value: [1, 2]
+    type: "line"
+]
+"#;
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Shapes {
+            shapes: Vec<Shape>,
+        }
+
+        let mut tree = Deserializer::from_str(text)
+            .unwrap()
+            .enum_repr(EnumRepr::type_value())
+            .variant_names(VariantNames::SnakeCase);
+        let expected = Shapes::deserialize(&mut tree).unwrap();
+        let mut direct = TextDeserializer::new(text)
+            .enum_repr(EnumRepr::type_value())
+            .variant_names(VariantNames::SnakeCase);
+        assert_eq!(Shapes::deserialize(&mut direct).unwrap(), expected);
+        direct.end().unwrap();
+
+        // Externally represented variants are the only key of their object
+        let text = "shapes: [\n    Circle: 1.5\n    Point: null\n]";
+        assert_eq!(
+            from_str::<Shapes>(text).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedEnum)
+        );
+        assert_eq!(
+            from_str_direct::<Shapes>(text).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedEnum)
+        );
+    }
+
+    #[test]
+    fn test_options() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Options {
+            float: f64,
+            int: u32,
+            lenient: u16,
+            unit: (),
+        }
+
+        let text = "float: 30\nint: 3.0\nlenient: \"8080\"\nunit: empty";
+        assert!(from_str_direct::<Options>(text).is_err());
+
+        let mut deserializer = TextDeserializer::new(text)
+            .int_to_float(true)
+            .float_to_int(true)
+            .lenient_strings(true)
+            .empty_as_unit(true);
+        let options = Options::deserialize(&mut deserializer).unwrap();
+        deserializer.end().unwrap();
+        assert_eq!(
+            options,
+            Options {
+                float: 30.0,
+                int: 3,
+                lenient: 8080,
+                unit: ()
+            }
+        );
+    }
+
+    #[test]
+    fn test_partial_reads() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct First {
+            a: u8,
+        }
+
+        // Unknown fields and extra elements are skipped but still validated
+        let text = "a: 1\nb:\n    c: [1, 2, [3]]\n    d: \"x\"\ne: (1, 2)";
+        assert!(from_str_direct::<First>(text).is_err());
+        let text = "a: 1\nb:\n    c: [1, 2, [3]]\n    d: \"x\"\ne: [1, 2, 3]";
+        assert_eq!(from_str_direct::<First>(text).unwrap(), First { a: 1 });
+
+        let text = "a: [1, 2, 3]";
+        let value: HashMap<String, (u8, u8)> = from_str_direct(text).unwrap();
+        assert_eq!(value["a"], (1, 2));
+
+        // Nothing after the entries visited is read until `end` is called
+        let text = "a: 1\nb: ]";
+        let mut deserializer = TextDeserializer::new(text);
+        let mut map = HashMap::new();
+        (&mut deserializer)
+            .deserialize_map(FirstEntry(&mut map))
+            .unwrap();
+        assert_eq!(map.get("a"), Some(&1));
+        assert!(deserializer.end().is_err());
+    }
+
+    #[test]
+    fn test_entries() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Tenant {
+            plan: String,
+            seats: u32,
+        }
+
+        fn entries(text: &str) -> Vec<(String, Result<Tenant, String>)> {
+            iter_entries::<Tenant>(text)
+                .map(|(key, value)| (key, value.map_err(|err| err.to_string())))
+                .collect()
+        }
+
+        // Entries that do not match the type do not stop the iteration
+        let text = "$plan: \"pro\"\n\nacme:\n    plan: $plan\n    seats: 12\n\n# Comment\nglobex:\n    plan: \"free\"\n    seats: \"many\"\ninitech:\n    plan: \"free\"\n    seats: 3\n";
+        let read = entries(text);
+        assert_eq!(read.len(), 3);
+        assert_eq!(
+            read[0],
+            (
+                "acme".to_string(),
+                Ok(Tenant {
+                    plan: "pro".to_string(),
+                    seats: 12
+                })
+            )
+        );
+        assert_eq!(read[1].0, "globex");
+        assert!(read[1].1.is_err());
+        assert_eq!(
+            read[2],
+            (
+                "initech".to_string(),
+                Ok(Tenant {
+                    plan: "free".to_string(),
+                    seats: 3
+                })
+            )
+        );
+
+        assert!(entries("").is_empty());
+        assert!(entries("# Only a comment\n$a: 1\n").is_empty());
+
+        // Syntax errors end the iteration, with the key of the entry they were
+        // found in when there is one
+        let text =
+            "acme:\n    plan: \"pro\"\n    seats: [1\nglobex:\n    plan: \"free\"\n    seats: 3";
+        let read = entries(text);
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].0, "acme");
+        assert!(read[0].1.is_err());
+
+        let text =
+            "acme:\n    plan: \"pro\"\n    seats: 1\nacme:\n    plan: \"free\"\n    seats: 3";
+        let read = entries(text);
+        assert_eq!(read.len(), 2);
+        assert!(read[0].1.is_ok());
+        assert_eq!(
+            read[1],
+            (
+                String::new(),
+                Err("Input text does not have a valid Gura format. Parsing failed with error \"The key \"acme\" has been already defined at line 1 column 1\" at line 4 column 1".to_string())
+            )
+        );
+
+        let text = "acme:\n    plan: \"pro\"\n    seats: 1\n]";
+        let read = entries(text);
+        assert_eq!(read.len(), 2);
+        assert!(read[0].1.is_ok());
+        assert_eq!(read[1].0, "");
+        assert!(read[1].1.is_err());
+
+        // Options are applied to every entry
+        let text = "a: 1\nb: 2.5";
+        let read: Vec<_> = TextDeserializer::new(text)
+            .int_to_float(true)
+            .into_entries::<f64>()
+            .map(|(key, value)| (key, value.unwrap()))
+            .collect();
+        assert_eq!(read, vec![("a".to_string(), 1.0), ("b".to_string(), 2.5)]);
+    }
+
+    #[test]
+    fn test_array_elements() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Event {
+            id: u32,
+            name: String,
+        }
+
+        fn event(id: u32, name: &str) -> Event {
+            Event {
+                id,
+                name: name.to_string(),
+            }
+        }
+
+        let text = "
+# Event catalog
+version: 2
+catalog:
+    owner:
+        name: \"ops\"
+    events: [
+        id: 1
+        name: \"start\",
+
+        id: \"two\"
+        name: \"broken\",
+
+        id: 3
+        name: \"stop\"
+    ]
+    other: [1, 2]
+footer: true
+";
+        let events: Vec<_> = iter_array::<Event>(text, "catalog.events")
+            .map(|event| event.map_err(Error::into_kind))
+            .collect();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0], Ok(event(1, "start")));
+        assert!(events[1].is_err());
+        assert_eq!(events[2], Ok(event(3, "stop")));
+
+        let numbers: Vec<u8> = iter_array(text, "catalog.other")
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(numbers, vec![1, 2]);
+
+        let numbers: Vec<Result<u8, Error>> = iter_array("a: []", "a").collect();
+        assert!(numbers.is_empty());
+
+        // Paths that do not lead to an array
+        let errors = [
+            (
+                "catalog.missing",
+                ErrorKind::MissingPath("catalog.missing".to_string()),
+            ),
+            ("catalog", ErrorKind::ExpectedArray),
+            ("version", ErrorKind::ExpectedArray),
+            ("version.events", ErrorKind::ExpectedMap),
+            ("", ErrorKind::MissingPath(String::new())),
+        ];
+        for (path, error) in errors {
+            let read: Vec<_> = iter_array::<Event>(text, path)
+                .map(|event| event.map_err(Error::into_kind))
+                .collect();
+            assert_eq!(read, vec![Err(error)], "{}", path);
+        }
+        let read: Vec<_> = iter_array::<Event>("", "a")
+            .map(|event| event.map_err(Error::into_kind))
+            .collect();
+        assert_eq!(read, vec![Err(ErrorKind::MissingPath("a".to_string()))]);
+
+        // The rest of the document is checked once the array ends
+        let text = "events: [\n    id: 1\n    name: \"start\"\n]\nfooter: ]";
+        let read: Vec<_> = iter_array::<Event>(text, "events")
+            .map(|event| event.map_err(Error::into_kind))
+            .collect();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0], Ok(event(1, "start")));
+        assert!(read[1].is_err());
+
+        // Syntax errors in the array end the iteration
+        let text = "events: [\n    id: 1\n    name: \"start\",\n    id: 2\n     name: \"x\"\n]";
+        let read: Vec<_> = iter_array::<Event>(text, "events")
+            .map(|event| event.map_err(Error::into_kind))
+            .collect();
+        assert_eq!(read.len(), 2);
+        assert!(read[1].is_err());
+
+        // Options are applied to every element
+        let read: Vec<f64> = TextDeserializer::new("a:\n    b: [1, 2.5]")
+            .int_to_float(true)
+            .into_array_elements("a.b")
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(read, vec![1.0, 2.5]);
+    }
+
+    #[test]
+    fn test_limits() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Config {
+            name: String,
+            tags: Vec<String>,
+            server: HashMap<String, u16>,
+        }
+
+        fn read<T>(text: &str, limits: Limits) -> Result<T, Error>
+        where
+            T: for<'de> Deserialize<'de>,
+        {
+            let mut deserializer = TextDeserializer::new(text).limits(limits);
+            let value = T::deserialize(&mut deserializer)?;
+            deserializer.end()?;
+            Ok(value)
+        }
+
+        // Same counts as `Deserializer::from_str_with_limits`: 2 levels and 8
+        // values, with "admin_port" as the longest string
+        let text = r#"
+name: "api"
+tags: ["a", "bb"]
+server:
+    port: 8080
+    admin_port: 8081
+"#;
+        let limits = Limits::new()
+            .max_depth(2)
+            .max_nodes(8)
+            .max_string_length(10)
+            .max_input_size(text.len());
+        assert!(read::<Config>(text, limits).is_ok());
+        for (limits, kind) in [
+            (Limits::new().max_depth(1), ErrorKind::DepthLimit(1)),
+            (Limits::new().max_nodes(7), ErrorKind::NodeLimit(7)),
+            (
+                Limits::new().max_string_length(9),
+                ErrorKind::StringLengthLimit(9),
+            ),
+            (
+                Limits::new().max_input_size(10),
+                ErrorKind::InputSizeLimit(10),
+            ),
+        ] {
+            assert_eq!(
+                read::<Config>(text, limits).map_err(Error::into_kind),
+                Err(kind)
+            );
+        }
+        assert_eq!(
+            read::<()>("", Limits::new().max_depth(0)).map_err(Error::into_kind),
+            Err(ErrorKind::DepthLimit(0))
+        );
+
+        // Values that are skipped are checked too
+        let text = "name: \"api\"\ntags: []\nserver:\n    port: 1\nother: [[1]]";
+        assert_eq!(
+            read::<Config>(text, Limits::new().max_depth(2)).map_err(Error::into_kind),
+            Err(ErrorKind::DepthLimit(2))
+        );
+
+        // The parser stops at the limit instead of reading the whole text
+        let text = format!("a: {}", "[".repeat(100_000));
+        assert_eq!(
+            read::<serde_json::Value>(&text, Limits::new().max_depth(64)).map_err(Error::into_kind),
+            Err(ErrorKind::DepthLimit(64))
+        );
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Server {
+            host: String,
+            ports: Vec<u16>,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Config {
+            name: String,
+            server: Server,
+        }
+
+        fn read<T>(text: &str, policy: DuplicateKeys) -> Result<T, Error>
+        where
+            T: for<'de> Deserialize<'de>,
+        {
+            let mut deserializer = TextDeserializer::new(text).duplicate_keys(policy);
+            let value = T::deserialize(&mut deserializer)?;
+            deserializer.end()?;
+            Ok(value)
+        }
+
+        fn config(name: &str, host: &str, ports: Vec<u16>) -> Config {
+            Config {
+                name: name.to_string(),
+                server: Server {
+                    host: host.to_string(),
+                    ports,
+                },
+            }
+        }
+
+        let text = "name: \"a\"\nserver:\n    host: \"x\"\n    ports: [1]\n    host: \"y\"\nname: \"b\"\nserver:\n    host: \"z\"\n    ports: [2, 3]";
+        assert_eq!(
+            read::<Config>(text, DuplicateKeys::Error).map_err(Error::into_kind),
+            Err(ErrorKind::Syntax(
+                "The key \"host\" has been already defined at line 3 column 5".to_string()
+            ))
+        );
+        assert_eq!(
+            read::<Config>(text, DuplicateKeys::FirstWins).unwrap(),
+            config("a", "x", vec![1])
+        );
+        assert_eq!(
+            read::<Config>(text, DuplicateKeys::LastWins).unwrap(),
+            config("b", "z", vec![2, 3])
+        );
+
+        // Maps keep the position of the value that wins
+        let text = "a: 1\nb: 2\na: 3";
+        let value: Vec<(String, u8)> = read::<Value>(text, DuplicateKeys::LastWins)
+            .map(|value| match value {
+                Value::Object(entries) => entries
+                    .into_iter()
+                    .map(|(key, value)| match value {
+                        Value::Integer(int) => (key, int as u8),
+                        _ => unreachable!(),
+                    })
+                    .collect(),
+                _ => unreachable!(),
+            })
+            .unwrap();
+        assert_eq!(value, vec![("b".to_string(), 2), ("a".to_string(), 3)]);
+        let value: HashMap<String, u8> = read(text, DuplicateKeys::FirstWins).unwrap();
+        assert_eq!(value["a"], 1);
+
+        // Skipped values are still checked
+        let text = "a: 1\na: [1,\nb: 2";
+        for policy in [DuplicateKeys::FirstWins, DuplicateKeys::LastWins] {
+            assert!(read::<HashMap<String, u8>>(text, policy).is_err());
+        }
+
+        // Entries left by the visitor are checked by `end`
+        let text = "name: \"a\"\nother: 1\nother: 2";
+        #[derive(Deserialize)]
+        struct Name {
+            #[allow(dead_code)]
+            name: String,
+        }
+        assert!(read::<Name>(text, DuplicateKeys::Error).is_err());
+        assert!(read::<Name>(text, DuplicateKeys::FirstWins).is_ok());
+
+        // Entries and array paths follow the policy too
+        let text = "a: 1\nb: 2\na: 3";
+        let read_entries = |policy| {
+            TextDeserializer::new(text)
+                .duplicate_keys(policy)
+                .into_entries::<u8>()
+                .map(|(key, value)| (key, value.unwrap()))
+                .collect::<Vec<_>>()
+        };
+        let entry = |key: &str, value| (key.to_string(), value);
+        assert_eq!(
+            read_entries(DuplicateKeys::FirstWins),
+            vec![entry("a", 1), entry("b", 2)]
+        );
+        assert_eq!(
+            read_entries(DuplicateKeys::LastWins),
+            vec![entry("b", 2), entry("a", 3)]
+        );
+
+        let text = "a: [1, 2]\nb:\n    c: [5]\n    c: [6]\na: [3, 4]\nb:\n    c: [7]";
+        let read_array = |policy, path| {
+            TextDeserializer::new(text)
+                .duplicate_keys(policy)
+                .into_array_elements::<u8>(path)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(Error::into_kind)
+        };
+        assert_eq!(read_array(DuplicateKeys::FirstWins, "a"), Ok(vec![1, 2]));
+        assert_eq!(read_array(DuplicateKeys::LastWins, "a"), Ok(vec![3, 4]));
+        assert_eq!(read_array(DuplicateKeys::FirstWins, "b.c"), Ok(vec![5]));
+        assert_eq!(read_array(DuplicateKeys::LastWins, "b.c"), Ok(vec![7]));
+        assert!(matches!(
+            read_array(DuplicateKeys::Error, "a"),
+            Err(ErrorKind::Syntax(_))
+        ));
+
+        // Variants written as objects
+        #[derive(Deserialize, Debug, PartialEq)]
+        enum Mode {
+            Fixed(u8),
+        }
+        let text = "mode:\n    Fixed: 1\n    Fixed: 2";
+        let mode = |policy| {
+            read::<HashMap<String, Mode>>(text, policy)
+                .unwrap()
+                .remove("mode")
+        };
+        assert_eq!(mode(DuplicateKeys::FirstWins), Some(Mode::Fixed(1)));
+        assert_eq!(mode(DuplicateKeys::LastWins), Some(Mode::Fixed(2)));
+
+        // Objects nested in entries and array elements
+        let text = "x:\n    a: 1\n    a: 2\n";
+        let nested_entry = |policy| {
+            TextDeserializer::new(text)
+                .duplicate_keys(policy)
+                .into_entries::<HashMap<String, u8>>()
+                .map(|(_, value)| value.map(|value| value["a"]))
+                .map(|value| value.map_err(Error::into_kind))
+                .collect::<Vec<_>>()
+        };
+        assert!(matches!(
+            nested_entry(DuplicateKeys::Error).as_slice(),
+            [Err(ErrorKind::Syntax(_))]
+        ));
+        assert_eq!(nested_entry(DuplicateKeys::FirstWins), vec![Ok(1)]);
+        assert_eq!(nested_entry(DuplicateKeys::LastWins), vec![Ok(2)]);
+        assert!(iter_entries::<HashMap<String, u8>>(text).any(|(_, value)| value.is_err()));
+
+        let text = "x: [\n    a: 1\n    a: 2\n]";
+        let nested_element = |policy| {
+            TextDeserializer::new(text)
+                .duplicate_keys(policy)
+                .into_array_elements::<HashMap<String, u8>>("x")
+                .map(|value| value.map(|value| value["a"]).map_err(Error::into_kind))
+                .collect::<Vec<_>>()
+        };
+        assert!(matches!(
+            nested_element(DuplicateKeys::Error).as_slice(),
+            [Err(ErrorKind::Syntax(_))]
+        ));
+        assert_eq!(nested_element(DuplicateKeys::FirstWins), vec![Ok(1)]);
+        assert_eq!(nested_element(DuplicateKeys::LastWins), vec![Ok(2)]);
+        assert!(iter_array::<HashMap<String, u8>>(text, "x").any(|value| value.is_err()));
+    }
+
+    /// Reads only the first entry of a map
+    struct FirstEntry<'a>(&'a mut HashMap<String, u8>);
+
+    impl<'de> Visitor<'de> for FirstEntry<'_> {
+        type Value = ();
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a map")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+            if let Some((key, value)) = map.next_entry()? {
+                self.0.insert(key, value);
+            }
+            Ok(())
+        }
+    }
+}
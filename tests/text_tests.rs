@@ -0,0 +1,349 @@
+#[cfg(test)]
+mod test_text_serializer {
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use std::rc::Rc;
+
+    use serde::ser::{Serialize, SerializeMap, Serializer};
+    use serde_derive::Serialize;
+    use serde_gura::{
+        to_string, to_string_direct, EnumRepr, Error, ErrorKind, Serializer as GuraSerializer,
+        TextSerializer, VariantNames,
+    };
+
+    /// Both serializers must write the same text
+    fn assert_same<T: Serialize>(value: &T, options: GuraSerializer) -> String {
+        let expected = options.to_string(value).unwrap();
+        let mut serializer = TextSerializer::from(options);
+        value.serialize(&mut serializer).unwrap();
+        assert_eq!(serializer.into_string(), expected);
+        expected
+    }
+
+    #[derive(Serialize)]
+    struct Empty {}
+
+    #[derive(Serialize)]
+    struct Inner {
+        name: &'static str,
+        values: Vec<i32>,
+        comment: Option<&'static str>,
+    }
+
+    #[derive(Serialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Line(u32, u32),
+        Rect { width: u32, label: Option<String> },
+        Nested(Inner),
+    }
+
+    #[derive(Serialize)]
+    struct Document {
+        title: &'static str,
+        escaped: &'static str,
+        integers: (i8, u64, i128, u128),
+        floats: (f32, f64, f64, f64),
+        flags: [bool; 2],
+        letter: char,
+        unit: (),
+        bytes: serde_bytes_like::Bytes,
+        empty: Empty,
+        empty_list: Vec<u8>,
+        inner: Inner,
+        objects: Vec<Inner>,
+        empties: Vec<Empty>,
+        nested_lists: Vec<Vec<Inner>>,
+        shapes: Vec<Shape>,
+        shape: Shape,
+        map: BTreeMap<&'static str, Vec<Inner>>,
+    }
+
+    // Serializes a byte slice with `serialize_bytes`
+    mod serde_bytes_like {
+        pub struct Bytes(pub &'static [u8]);
+
+        impl serde::Serialize for Bytes {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_bytes(self.0)
+            }
+        }
+    }
+
+    fn inner(name: &'static str) -> Inner {
+        Inner {
+            name,
+            values: vec![1, -2],
+            comment: None,
+        }
+    }
+
+    fn document() -> Document {
+        let mut map = BTreeMap::new();
+        map.insert("first", vec![inner("a"), inner("b")]);
+        map.insert("second", Vec::new());
+
+        Document {
+            title: "Gura",
+            escaped: "tab\t \"quote\" new\nline \\",
+            integers: (-8, u64::MAX, i128::MIN, u128::MAX / 4),
+            floats: (0.1, -0.0, f64::NAN, f64::NEG_INFINITY),
+            flags: [true, false],
+            letter: 'ñ',
+            unit: (),
+            bytes: serde_bytes_like::Bytes(&[0, 255]),
+            empty: Empty {},
+            empty_list: Vec::new(),
+            inner: inner("inner"),
+            objects: vec![inner("x"), inner("y")],
+            empties: vec![Empty {}, Empty {}],
+            nested_lists: vec![vec![inner("n")], Vec::new()],
+            shapes: vec![
+                Shape::Point,
+                Shape::Circle(1.5),
+                Shape::Line(1, 2),
+                Shape::Rect {
+                    width: 3,
+                    label: None,
+                },
+                Shape::Nested(inner("s")),
+            ],
+            shape: Shape::Rect {
+                width: 1,
+                label: Some("r".to_string()),
+            },
+            map,
+        }
+    }
+
+    #[test]
+    fn test_same_output() {
+        let document = document();
+        assert_same(&document, GuraSerializer::new());
+        assert_same(&document, GuraSerializer::new().skip_none_fields(true));
+        assert_eq!(
+            to_string_direct(&document).unwrap(),
+            to_string(&document).unwrap()
+        );
+
+        for repr in [EnumRepr::External, EnumRepr::type_value()].iter() {
+            for names in [VariantNames::AsIs, VariantNames::SnakeCase].iter() {
+                for as_index in [false, true].iter() {
+                    let options = GuraSerializer::new()
+                        .enum_repr(*repr)
+                        .variant_names(*names)
+                        .unit_variants_as_index(*as_index);
+                    assert_same(&document, options);
+                    assert_same(&document.shapes, options);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_top_level_values() {
+        let options = GuraSerializer::new();
+        assert_eq!(assert_same(&Empty {}, options), "");
+        assert_eq!(assert_same(&"text", options), "\"text\"");
+        assert_eq!(assert_same(&Some(1.5), options), "1.5");
+        assert_eq!(assert_same(&None::<u8>, options), "null");
+        assert_eq!(assert_same(&(), options), "null");
+        assert_eq!(assert_same(&Vec::<u8>::new(), options), "[]");
+        assert_same(&Shape::Point, options);
+        assert_same(&Shape::Circle(1.0), options);
+        assert_same(&vec![inner("a")], options);
+        assert_same(&vec![Empty {}], options);
+    }
+
+    #[test]
+    fn test_mixed_arrays() {
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum Mixed {
+            Int(i32),
+            Text(&'static str),
+            List(Vec<Mixed>),
+            Object(Inner),
+            Empty(Empty),
+        }
+
+        // Elements written before the first object are moved to their own lines
+        let arrays = vec![
+            vec![
+                Mixed::Int(1),
+                Mixed::Text("a, b"),
+                Mixed::Object(inner("o")),
+            ],
+            vec![
+                Mixed::List(vec![Mixed::Object(inner("deep")), Mixed::Int(2)]),
+                Mixed::Empty(Empty {}),
+                Mixed::Object(inner("o")),
+                Mixed::Int(3),
+            ],
+            vec![
+                Mixed::Empty(Empty {}),
+                Mixed::List(vec![Mixed::List(vec![Mixed::Object(inner("x"))])]),
+            ],
+        ];
+
+        let options = GuraSerializer::new();
+        assert_eq!(
+            assert_same(&arrays[0], options),
+            "[\n    1,\n    \"a, b\",\n    name: \"o\"\n    values: [1, -2]\n    comment: null\n]"
+        );
+        assert_same(&arrays, options);
+
+        let mut map = BTreeMap::new();
+        map.insert("arrays", arrays);
+        assert_same(&map, options);
+    }
+
+    #[test]
+    fn test_errors() {
+        #[derive(Serialize)]
+        struct Renamed {
+            #[serde(rename = "max-connections")]
+            max_connections: u32,
+        }
+
+        struct Repeated;
+
+        impl Serialize for Repeated {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("a", &1)?;
+                map.serialize_entry("a", &2)?;
+                map.end()
+            }
+        }
+
+        struct KeyWithoutValue;
+
+        impl Serialize for KeyWithoutValue {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_key("a")?;
+                map.end()
+            }
+        }
+
+        struct ValueWithoutKey;
+
+        impl Serialize for ValueWithoutKey {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_value(&1)?;
+                map.end()
+            }
+        }
+
+        let renamed = Renamed { max_connections: 1 };
+        assert_eq!(
+            to_string_direct(&renamed).map_err(Error::into_kind),
+            Err(ErrorKind::InvalidKey("max-connections".to_string()))
+        );
+        assert_eq!(
+            to_string_direct(&Repeated).map_err(Error::into_kind),
+            Err(ErrorKind::DuplicateKey("a".to_string()))
+        );
+        assert_eq!(
+            to_string_direct(&KeyWithoutValue).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedMapValue)
+        );
+        assert_eq!(
+            to_string_direct(&ValueWithoutKey).map_err(Error::into_kind),
+            Err(ErrorKind::ExpectedMapKey)
+        );
+
+        let map: BTreeMap<(u8, u8), u8> = vec![((1, 2), 1)].into_iter().collect();
+        assert_eq!(
+            to_string_direct(&map).map_err(Error::into_kind),
+            Err(ErrorKind::KeyMustBeAString)
+        );
+    }
+
+    #[test]
+    fn test_max_depth() {
+        /// Both serializers must fail at the same depth
+        fn assert_same_depth<T: Serialize>(value: &T, options: GuraSerializer) {
+            for depth in 0..5 {
+                let options = options.max_depth(depth);
+                let expected = options.to_string(value).map_err(Error::into_kind);
+                let mut serializer = TextSerializer::from(options);
+                let written = value
+                    .serialize(&mut serializer)
+                    .map(|_| serializer.into_string())
+                    .map_err(Error::into_kind);
+                assert_eq!(written, expected, "depth {}", depth);
+            }
+        }
+
+        let mut map = BTreeMap::new();
+        map.insert("a", vec![vec![1], vec![]]);
+        let shapes = vec![
+            Shape::Point,
+            Shape::Line(1, 2),
+            Shape::Rect {
+                width: 3,
+                label: None,
+            },
+            Shape::Nested(Inner {
+                name: "x",
+                values: vec![1],
+                comment: None,
+            }),
+        ];
+        for options in [
+            GuraSerializer::new(),
+            GuraSerializer::new().enum_repr(EnumRepr::type_value()),
+        ] {
+            assert_same_depth(&map, options);
+            assert_same_depth(&shapes, options);
+            assert_same_depth(&vec![Empty {}], options);
+            assert_same_depth(&Empty {}, options);
+        }
+
+        // Cycles fail instead of overflowing the stack
+        struct Cycle(RefCell<Option<Rc<Cycle>>>);
+
+        impl Serialize for Cycle {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("next", &self.0.borrow().as_deref())?;
+                map.end()
+            }
+        }
+
+        let root = Rc::new(Cycle(RefCell::new(None)));
+        *root.0.borrow_mut() = Some(Rc::clone(&root));
+        let mut serializer = TextSerializer::from(GuraSerializer::new().max_depth(100));
+        assert_eq!(
+            root.serialize(&mut serializer).map_err(Error::into_kind),
+            Err(ErrorKind::DepthLimit(100))
+        );
+        *root.0.borrow_mut() = None;
+
+        // So do deeply nested newtype structs and options
+        #[derive(Serialize)]
+        struct Recursive(Option<Box<Recursive>>);
+
+        impl Drop for Recursive {
+            fn drop(&mut self) {
+                let mut next = self.0.take();
+                while let Some(mut node) = next {
+                    next = node.0.take();
+                }
+            }
+        }
+
+        let deep = (0..100_000).fold(Recursive(None), |next, _| Recursive(Some(Box::new(next))));
+        let mut serializer = TextSerializer::from(GuraSerializer::new().max_depth(100));
+        assert_eq!(
+            deep.serialize(&mut serializer).map_err(Error::into_kind),
+            Err(ErrorKind::DepthLimit(100))
+        );
+        let shallow = (0..50).fold(Recursive(None), |next, _| Recursive(Some(Box::new(next))));
+        assert_same_depth(&shallow, GuraSerializer::new());
+    }
+}
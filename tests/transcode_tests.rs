@@ -0,0 +1,204 @@
+#[cfg(feature = "json")]
+mod test_json {
+    use gura::GuraType;
+    use serde_gura::json::{from_gura, to_gura, GuraValue};
+    use serde_gura::ErrorKind;
+    use serde_json::json;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_round_trip() {
+        let json = r#"{
+  "name": "Gura",
+  "version": 1,
+  "ratio": 0.5,
+  "big": 18446744073709551615,
+  "enabled": true,
+  "nothing": null,
+  "tags": [
+    "a",
+    "b"
+  ]
+}"#;
+        let gura = to_gura(json).unwrap();
+        let expected = r#"name: "Gura"
+version: 1
+ratio: 0.5
+big: 18446744073709551615
+enabled: true
+nothing: null
+tags: ["a", "b"]"#;
+        assert_eq!(gura, expected);
+        assert_eq!(from_gura(&gura).unwrap(), json);
+    }
+
+    #[test]
+    fn test_unsupported_values() {
+        let err = from_gura("values: [1.5, nan]").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Float `NaN` at `values[1]` can not be represented in JSON"
+        );
+
+        assert!(to_gura(r#"{"a": 1} {"b": 2}"#).is_err());
+
+        // Keys must be valid Gura keys
+        let err = to_gura(r#"{"a b": 1}"#).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::InvalidKey("a b".to_string()));
+        let err = to_gura(r#"{"a": [{"b": {"": 1}}]}"#).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::InvalidKey(String::new()));
+    }
+
+    #[test]
+    fn test_documents() {
+        for (json, root) in [
+            ("[1, 2]", "an array"),
+            (r#""s""#, "a string"),
+            ("1.5", "a number"),
+            ("true", "a boolean"),
+            ("null", "null"),
+        ] {
+            let err = to_gura(json).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("Gura documents must be objects, but the root is {}", root)
+            );
+        }
+
+        // Empty objects are empty documents, nested ones are `empty`
+        assert_eq!(to_gura("{}").unwrap(), "");
+        assert_eq!(to_gura(r#"{"a": {}}"#).unwrap(), "a: empty");
+        for json in ["{}", r#"{"a": {}}"#, r#"{"a": [{"b": 1}]}"#] {
+            gura::parse(&to_gura(json).unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_value_conversions() {
+        let json = json!({
+            "int": 1,
+            "float": 1.0,
+            "big": u64::MAX,
+            "nested": {"values": [null, true, "a"]}
+        });
+        let gura = GuraType::from(GuraValue::from(json.clone()));
+        let expected = gura::parse(
+            r#"
+int: 1
+float: 1.0
+big: 18446744073709551615
+nested:
+    values: [null, true, "a"]"#,
+        )
+        .unwrap();
+        assert_eq!(gura, expected);
+
+        let back = serde_json::Value::try_from(GuraValue(gura)).unwrap();
+        assert_eq!(back, json);
+        assert!(back["int"].is_i64());
+        assert!(back["float"].is_f64());
+        assert!(back["big"].is_u64());
+
+        for value in [
+            GuraType::BigInteger(i128::MAX),
+            GuraType::Float(f64::INFINITY),
+        ] {
+            assert!(serde_json::Value::try_from(&GuraValue(value)).is_err());
+        }
+    }
+}
+
+#[cfg(feature = "toml")]
+mod test_toml {
+    use serde_gura::toml::{from_gura, to_gura};
+
+    #[test]
+    fn test_round_trip() {
+        let toml = r#"title = "Gura"
+
+[database]
+ip = "127.0.0.1"
+port = [80, 8080]
+enabled = true
+"#;
+        let gura = to_gura(toml).unwrap();
+        let expected = r#"title: "Gura"
+database:
+    ip: "127.0.0.1"
+    port: [80, 8080]
+    enabled: true"#;
+        assert_eq!(gura, expected);
+        assert_eq!(from_gura(&gura).unwrap(), toml);
+    }
+
+    #[test]
+    fn test_unsupported_values() {
+        let err = to_gura("[owner]\ndob = 1979-05-27T07:32:00Z").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Datetime `1979-05-27T07:32:00Z` at `owner.dob` can not be represented in Gura"
+        );
+
+        let err = from_gura("owner:\n    name: null").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Null value at `owner.name` can not be represented in TOML"
+        );
+    }
+
+    #[test]
+    fn test_documents() {
+        assert_eq!(to_gura("").unwrap(), "");
+        assert_eq!(to_gura("[a]").unwrap(), "a: empty");
+    }
+}
+
+#[cfg(feature = "yaml")]
+mod test_yaml {
+    use serde_gura::yaml::{from_gura, to_gura};
+    use serde_gura::ErrorKind;
+
+    #[test]
+    fn test_round_trip() {
+        let yaml = r#"name: Gura
+version: 1
+ratio: .inf
+nothing: null
+servers:
+- ip: 127.0.0.1
+  port: 80
+"#;
+        let gura = to_gura(yaml).unwrap();
+        let expected = r#"name: "Gura"
+version: 1
+ratio: inf
+nothing: null
+servers: [
+    ip: "127.0.0.1"
+    port: 80
+]"#;
+        assert_eq!(gura, expected);
+        assert_eq!(from_gura(&gura).unwrap(), yaml);
+    }
+
+    #[test]
+    fn test_unsupported_values() {
+        for yaml in &["1: a", "true: b", "servers:\n  80: web"] {
+            let err = to_gura(yaml).unwrap_err();
+            assert_eq!(err.kind(), &ErrorKind::KeyMustBeAString, "{}", yaml);
+        }
+
+        // Quoted keys are strings
+        assert_eq!(to_gura("\"1\": a").unwrap(), "1: \"a\"");
+
+        let err = to_gura("a-b: 1").unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::InvalidKey("a-b".to_string()));
+        for yaml in ["- 1\n- 2", "text", "~"] {
+            assert!(to_gura(yaml)
+                .unwrap_err()
+                .to_string()
+                .starts_with("Gura documents must be objects"));
+        }
+        assert_eq!(to_gura("{}").unwrap(), "");
+    }
+}